@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+
+const BSP_IDENT: &[u8] = b"IBSP";
+// 46 is q3, 47 is quake live, the entity lump is the same in both
+const BSP_VERSIONS: [i32; 2] = [46, 47];
+const HEADER_LEN: usize = 8 + 17 * 8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SpawnCounts {
+	pub ffa: u16,
+	pub red: u16,
+	pub blue: u16,
+}
+
+/*
+    what a map contains according to its entity lump, counts are keyed by classname
+    spawns: info_player_deathmatch for ffa, team_CTF_red/blue spawn and player for teams
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LevelEntities {
+	pub spawns: SpawnCounts,
+	pub weapons: BTreeMap<String, u16>,
+	pub items: BTreeMap<String, u16>,
+	pub armor: BTreeMap<String, u16>,
+	pub powerups: BTreeMap<String, u16>,
+	pub message: String,
+	pub music: String,
+	pub ctf_flags: bool,
+	pub neutral_flag: bool,
+	#[serde(default)]
+	pub race_timers: bool,
+}
+
+impl LevelEntities {
+	// seeks from the header straight to the entity lump, only the two are read
+	pub fn from_bsp<R: Read + Seek>(bsp: R) -> Result<Self, Error> {
+		let entities = read_entity_lump(bsp, |bsp, offset| bsp.seek(SeekFrom::Start(offset)).map(|_| ()))?;
+
+		Ok(Self::from_entities(&parse_entities(&entities)))
+	}
+
+	/*
+	    for bsps deflated in their pk3, those can't seek so everything before the entity lump is inflated and thrown away
+	    q3map2 writes the entity lump after most of the others, reading stops once it's done
+	*/
+	pub fn from_bsp_stream<R: Read>(bsp: R) -> Result<Self, Error> {
+		let entities = read_entity_lump(bsp, |bsp, offset| {
+			std::io::copy(&mut bsp.take(offset - HEADER_LEN as u64), &mut std::io::sink()).map(|_| ())
+		})?;
+
+		Ok(Self::from_entities(&parse_entities(&entities)))
+	}
+
+	pub fn from_entities(entities: &[HashMap<String, String>]) -> Self {
+		let mut level_entities = LevelEntities::default();
+		let mut red_flag = false;
+		let mut blue_flag = false;
+		let mut start_timer = false;
+		let mut stop_timer = false;
+
+		for entity in entities {
+			let classname = match entity.get("classname") {
+				Some(c) => c.to_lowercase(),
+				None => continue,
+			};
+
+			match classname.as_str() {
+				"worldspawn" => {
+					level_entities.message = entity.get("message").cloned().unwrap_or_default();
+					level_entities.music = entity.get("music").or(entity.get("noise")).cloned().unwrap_or_default();
+				}
+				"info_player_deathmatch" | "info_player_start" => level_entities.spawns.ffa += 1,
+				"team_ctf_redspawn" | "team_ctf_redplayer" => level_entities.spawns.red += 1,
+				"team_ctf_bluespawn" | "team_ctf_blueplayer" => level_entities.spawns.blue += 1,
+				"team_ctf_redflag" => red_flag = true,
+				"team_ctf_blueflag" => blue_flag = true,
+				"team_ctf_neutralflag" => level_entities.neutral_flag = true,
+				"target_starttimer" => start_timer = true,
+				"target_stoptimer" => stop_timer = true,
+				c if c.starts_with("weapon_") => *level_entities.weapons.entry(classname).or_default() += 1,
+				c if c.starts_with("item_armor_") => *level_entities.armor.entry(classname).or_default() += 1,
+				"item_quad" | "item_enviro" | "item_haste" | "item_invis" | "item_regen" | "item_flight" => {
+					*level_entities.powerups.entry(classname).or_default() += 1
+				}
+				c if c.starts_with("item_") || c.starts_with("ammo_") || c.starts_with("holdable_") => {
+					*level_entities.items.entry(classname).or_default() += 1
+				}
+				_ => continue,
+			}
+		}
+
+		level_entities.ctf_flags = red_flag && blue_flag;
+		level_entities.race_timers = start_timer && stop_timer;
+
+		level_entities
+	}
+
+	/*
+	    the same gametype names .arena files use, for maps that don't ship one
+	    deathmatch spawns are enough for ffa and tourney, team games fall back to them too when there are no team spawns
+	*/
+	pub fn infer_gametypes(&self) -> Vec<String> {
+		let mut gametypes: Vec<&str> = vec![];
+
+		if self.race_timers {
+			return vec![String::from("race")];
+		}
+
+		if self.spawns.ffa > 0 {
+			gametypes.extend(["ffa", "tourney"]);
+		}
+
+		if self.spawns.ffa > 0 || (self.spawns.red > 0 && self.spawns.blue > 0) {
+			gametypes.push("team");
+		}
+
+		if self.ctf_flags {
+			gametypes.push("ctf");
+		}
+
+		if self.ctf_flags && self.neutral_flag {
+			gametypes.push("oneflag");
+		}
+
+		gametypes.into_iter().map(String::from).collect()
+	}
+}
+
+pub const LUMP_ENTITIES: usize = 0;
+pub const LUMP_SHADERS: usize = 1;
+pub const LUMP_MODELS: usize = 7;
+pub const LUMP_DRAWVERTS: usize = 10;
+pub const LUMP_DRAWINDEXES: usize = 11;
+pub const LUMP_FOGS: usize = 12;
+pub const LUMP_SURFACES: usize = 13;
+
+pub const SURF_SKY: i32 = 0x4;
+pub const SURF_NODRAW: i32 = 0x80;
+
+// a whole bsp in memory, for when more than the entities is needed
+pub struct BspFile {
+	data: Vec<u8>,
+}
+
+impl BspFile {
+	pub fn read<R: Read>(mut bsp: R) -> Result<Self, Error> {
+		let mut data: Vec<u8> = vec![];
+		bsp.read_to_end(&mut data)?;
+
+		if data.len() < HEADER_LEN {
+			return Err(Error::new(ErrorKind::UnexpectedEof, "bsp is smaller than its header"));
+		}
+
+		check_header(&data[..HEADER_LEN])?;
+
+		Ok(Self { data })
+	}
+
+	pub fn lump(&self, index: usize) -> Result<&[u8], Error> {
+		let (offset, length) = lump_bounds(&self.data[..HEADER_LEN], index);
+
+		self.data
+			.get(offset..offset + length)
+			.ok_or(Error::new(ErrorKind::InvalidData, format!("lump {} is out of bounds", index)))
+	}
+
+	pub fn entities(&self) -> Result<Vec<HashMap<String, String>>, Error> {
+		let lump = String::from_utf8_lossy(self.lump(LUMP_ENTITIES)?);
+
+		Ok(parse_entities(lump.trim_end_matches('\0')))
+	}
+
+	// shader lump entries are a 64 byte nul padded name, surface flags and contents
+	pub fn shaders(&self) -> Result<Vec<(String, i32)>, Error> {
+		Ok(self
+			.lump(LUMP_SHADERS)?
+			.chunks_exact(72)
+			.map(|s| (padded_name(&s[..64]), i32::from_le_bytes(s[64..68].try_into().unwrap())))
+			.collect())
+	}
+
+	// fogs name their shader the same way, followed by the brush and its visible side
+	pub fn fog_shaders(&self) -> Result<Vec<String>, Error> {
+		Ok(self.lump(LUMP_FOGS)?.chunks_exact(72).map(|f| padded_name(&f[..64])).collect())
+	}
+}
+
+fn padded_name(name: &[u8]) -> String {
+	let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+
+	String::from_utf8_lossy(&name[..end]).to_string()
+}
+
+// header is "IBSP", version, then 17 lumps of (offset, length), little endian
+fn check_header(header: &[u8]) -> Result<(), Error> {
+	if &header[0..4] != BSP_IDENT {
+		return Err(Error::new(ErrorKind::InvalidData, "not an IBSP file"));
+	}
+
+	let version = i32::from_le_bytes(header[4..8].try_into().unwrap());
+
+	if !BSP_VERSIONS.contains(&version) {
+		return Err(Error::new(ErrorKind::InvalidData, format!("unsupported bsp version {}", version)));
+	}
+
+	Ok(())
+}
+
+fn lump_bounds(header: &[u8], index: usize) -> (usize, usize) {
+	let at = 8 + index * 8;
+	let offset = u32::from_le_bytes(header[at..at + 4].try_into().unwrap()) as usize;
+	let length = u32::from_le_bytes(header[at + 4..at + 8].try_into().unwrap()) as usize;
+
+	(offset, length)
+}
+
+// lump 0 is the entity text, it can be anywhere after the header so seek_to moves bsp to the offset the header gives
+fn read_entity_lump<R: Read>(mut bsp: R, seek_to: impl FnOnce(&mut R, u64) -> Result<(), Error>) -> Result<String, Error> {
+	let mut header = [0u8; HEADER_LEN];
+	bsp.read_exact(&mut header)?;
+
+	check_header(&header)?;
+
+	let (offset, length) = lump_bounds(&header, LUMP_ENTITIES);
+
+	if offset < HEADER_LEN {
+		return Err(Error::new(ErrorKind::InvalidData, "entity lump overlaps the bsp header"));
+	}
+
+	seek_to(&mut bsp, offset as u64)?;
+
+	let mut entities: Vec<u8> = Vec::with_capacity(length);
+	(&mut bsp).take(length as u64).read_to_end(&mut entities)?;
+
+	if entities.len() < length {
+		return Err(Error::new(ErrorKind::UnexpectedEof, "entity lump is cut short"));
+	}
+
+	Ok(String::from_utf8_lossy(&entities).trim_end_matches('\0').to_string())
+}
+
+// { "key" "value" ... } blocks, keys are lowercased, the first value for a key wins like in G_SpawnString
+pub fn parse_entities(lump: &str) -> Vec<HashMap<String, String>> {
+	let mut entities: Vec<HashMap<String, String>> = vec![];
+	let mut current: Option<HashMap<String, String>> = None;
+	let mut tokens: Vec<String> = vec![];
+	let mut chars = lump.chars();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'{' => current = Some(HashMap::new()),
+			'}' => {
+				if let Some(entity) = current.take() {
+					entities.push(entity);
+				}
+				tokens.clear();
+			}
+			'"' => {
+				let token: String = chars.by_ref().take_while(|c| *c != '"').collect();
+				tokens.push(token);
+
+				if tokens.len() == 2 {
+					if let Some(entity) = current.as_mut() {
+						entity.entry(tokens[0].to_lowercase()).or_insert(tokens[1].clone());
+					}
+					tokens.clear();
+				}
+			}
+			_ => continue,
+		}
+	}
+
+	entities
+}
@@ -3,5 +3,6 @@ pub mod config;
 pub mod demo;
 pub mod level;
 pub mod master;
+pub mod rcon;
 pub mod server;
 pub mod util;
\ No newline at end of file
@@ -11,7 +11,11 @@ use tauri_plugin_dialog::DialogExt;
 
 use crate::client::{Q3ExecableConfig, Q3Executable};
 use crate::config::SargeLauncher;
-use crate::q3_util::{get_defrag_recs, get_q3_configs, read_q3config};
+use crate::commands::level::client_vfs;
+use crate::pk3_checksum::{pak_checksums, PakChecksum};
+use crate::pk3_report::{override_report, OverrideReport};
+use crate::q3_util::{get_defrag_recs, read_q3config};
+use crate::vfs::SourceKind;
 
 #[tauri::command(async)]
 pub async fn pick_client(app: AppHandle) -> Result<Option<Q3Executable>, String> {
@@ -96,6 +100,10 @@ pub async fn kill_q3_client(app: AppHandle, process_id: Option<u32>) -> Result<(
 */
 #[tauri::command(async)]
 pub async fn get_client_search_paths(app: AppHandle, client: Q3Executable) -> Result<Vec<String>, tauri::Error> {
+	client_search_paths(&app, &client)
+}
+
+pub fn client_search_paths(app: &AppHandle, client: &Q3Executable) -> Result<Vec<String>, tauri::Error> {
     let mut search_paths: Vec<String> = vec![];
     let home = app.path().home_dir()?;
 
@@ -171,19 +179,37 @@ pub async fn get_defrag_rec_files(search_paths: Vec<String>) -> Result<HashMap<S
 }
 
 #[tauri::command(async)]
-pub async fn get_client_available_configs(search_paths: Vec<String>) -> Result<Vec<Q3ExecableConfig>, tauri::Error> {
-    let mut q3_configs: Vec<Q3ExecableConfig> = vec![];
-
-    for p in search_paths {
-        let path = Path::new(&p);
-        if path.is_dir() {
-            q3_configs.append(&mut get_q3_configs(path).await?);
-        }
-    }
+pub async fn get_client_available_configs(app: AppHandle, search_paths: Vec<String>) -> Result<Vec<Q3ExecableConfig>, tauri::Error> {
+    let vfs = client_vfs(&app, &search_paths)?;
+
+    // only loose configs, they're the ones that can be opened and edited
+    let q3_configs: Vec<Q3ExecableConfig> = vfs
+        .files()
+        .filter(|f| vfs.source(f).kind == SourceKind::Dir && f.name.to_lowercase().ends_with(".cfg"))
+        .map(|f| Q3ExecableConfig {
+            name: f.name.rsplit('/').next().unwrap().to_string(),
+            path: vfs.disk_path(f).to_str().unwrap().to_string(),
+        })
+        .collect();
 
 	Ok(q3_configs)
 }
 
+#[tauri::command(async)]
+pub async fn get_pk3_overrides(app: AppHandle, search_paths: Vec<String>) -> Result<OverrideReport, tauri::Error> {
+    let vfs = client_vfs(&app, &search_paths)?;
+
+	Ok(override_report(&vfs))
+}
+
+// pure checksums need the feed a server sent, without one only the plain checksums are filled in
+#[tauri::command(async)]
+pub async fn get_pk3_checksums(app: AppHandle, search_paths: Vec<String>, checksum_feed: Option<i32>) -> Result<Vec<PakChecksum>, tauri::Error> {
+    let vfs = client_vfs(&app, &search_paths)?;
+
+	Ok(pak_checksums(&vfs, checksum_feed))
+}
+
 #[tauri::command(async)]
 pub async fn get_client_q3config(search_paths: Vec<String>) -> Result<HashMap<String, HashMap<String, String>>, tauri::Error> {
 	let mut q3config: HashMap<String, HashMap<String, String>> = HashMap::new();
@@ -22,7 +22,7 @@ pub async fn save_config(app: AppHandle, updated_config: Config) -> Result<(), t
 }
 
 #[tauri::command(async)]
-pub async fn save_app_data(app: AppHandle, updated_data: AppData) -> Result<(), tauri::Error> {
+pub async fn save_app_data(app: AppHandle, mut updated_data: AppData) -> Result<(), tauri::Error> {
 	let state = app.state::<Mutex<SargeLauncher>>();
 	let mut app_data_dir = app.path().app_data_dir()?;
 
@@ -31,8 +31,17 @@ pub async fn save_app_data(app: AppHandle, updated_data: AppData) -> Result<(),
 	}
 
 	app_data_dir.push("appdata.json");
+
+	let state = state.lock().unwrap();
+	let mut app_data = state.app_data.lock().unwrap();
+
+	// rcon passwords and history are only changed through the rcon commands, keep ours over the frontend's copy
+	if let Some(current) = &*app_data {
+		updated_data.rcon = current.rcon.clone();
+	}
+
     updated_data.write_to_file(&app_data_dir)?;
-    state.lock().unwrap().app_data.lock().unwrap().replace(updated_data);
+    app_data.replace(updated_data);
 
 	Ok(())
 }
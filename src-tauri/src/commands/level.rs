@@ -1,15 +1,26 @@
 use image::DynamicImage;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{create_dir, File};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
-use zip::ZipArchive;
-use std::io::Read;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 
+use crate::bsp::BspFile;
+use crate::config::SargeLauncher;
 use crate::level::Level;
+use crate::level_index::LevelIndex;
+use crate::map_deps::{check_dependencies, MapDependencies};
+use crate::overview::render_overview;
+use crate::vfs::{Vfs, VfsFile, VfsSource, VfsReader};
+
+// overviews share the levelshots cache, levelshots/<map>.overview.png
+const OVERVIEW_SUFFIX: &str = ".overview";
+
+// each holds a pk3 file list and checksums, enough for a couple of clients and the mods they joined
+const MAX_CACHED_VFS: usize = 4;
 
 #[tauri::command(async)]
 pub async fn get_cached_levelshots(app: AppHandle) -> Result<HashMap<String, String>, tauri::Error> {
@@ -21,31 +32,94 @@ pub async fn get_cached_levelshots(app: AppHandle) -> Result<HashMap<String, Str
 		create_dir(&cache_dir)?;
 	}
 
+	let mut overviews: Vec<(String, String)> = vec![];
+
 	for entry in std::fs::read_dir(cache_dir)? {
 		let entry = entry?;
 		let path = entry.path();
 		let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+
+		if let Some(level_name) = name.strip_suffix(OVERVIEW_SUFFIX) {
+			overviews.push((level_name.to_lowercase(), path.to_str().unwrap().to_string()));
+			continue;
+		}
+
 		levelshots.entry(name.to_lowercase()).or_insert(path.to_str().unwrap().to_string());
 	}
 
+	// a rendered overview stands in for maps that don't ship a levelshot
+	for (level_name, path) in overviews {
+		levelshots.entry(level_name).or_insert(path);
+	}
+
 	Ok(levelshots)
 }
 
 #[tauri::command(async)]
-pub async fn get_levels(search_paths: Vec<String>, get_all_data: bool) -> Result<Vec<Level>, tauri::Error> {
-	let mut levels: Vec<Level> = vec![];
+pub async fn get_levels(app: AppHandle, search_paths: Vec<String>, get_all_data: bool) -> Result<Vec<Level>, tauri::Error> {
+	let index_path = level_index_path(&app)?;
 
-	for p in search_paths {
-		let path = Path::new(&p);
-		levels.append(&mut Level::get_q3_levels(path, get_all_data).await?);
+	// cloned out so the scan doesn't hold the state lock
+	let index_lock = app.state::<Mutex<SargeLauncher>>().lock().unwrap().level_index.clone();
+	let _index_guard = index_lock.lock().unwrap();
+
+	let mut index = LevelIndex::read_from_file(&index_path);
+	let (levels, changed) = index.levels(&search_paths, get_all_data)?;
+
+	// the index only saves scan time, a failed write is logged and otherwise ignored
+	if changed {
+		if let Err(e) = index.write_to_file(&index_path) {
+			log::error!("Could not write the level index: {}", e);
+		}
 	}
 
 	Ok(levels)
 }
 
+fn level_index_path(app: &AppHandle) -> Result<PathBuf, tauri::Error> {
+	let mut index_path = app.path().app_cache_dir()?;
+
+	if !index_path.exists() {
+		create_dir(&index_path)?;
+	}
+
+	index_path.push("levels.json");
+
+	Ok(index_path)
+}
+
+#[tauri::command(async)]
+pub async fn check_map_dependencies(app: AppHandle, search_paths: Vec<String>, level_name: String) -> Result<MapDependencies, tauri::Error> {
+	let vfs = client_vfs(&app, &search_paths)?;
+
+	Ok(check_dependencies(&vfs, &level_name)?)
+}
+
+/*
+    vfs indexes are kept in state per search path set and reused until any file in them changes
+    checking that only walks the directories, building one opens every pk3
+    a few are kept so checking a mod server before joining doesn't throw away the client's own index
+*/
+pub fn client_vfs(app: &AppHandle, search_paths: &[String]) -> Result<Arc<Vfs>, std::io::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let cached = state.lock().unwrap().vfs.lock().unwrap().iter().find(|v| v.search_paths == search_paths).cloned();
+
+	let vfs = match cached.filter(|v| v.is_current(search_paths)) {
+		Some(vfs) => vfs,
+		None => Arc::new(Vfs::build(search_paths)?),
+	};
+
+	let state = state.lock().unwrap();
+	let mut cache = state.vfs.lock().unwrap();
+	cache.retain(|v| v.search_paths != search_paths);
+	cache.insert(0, Arc::clone(&vfs));
+	cache.truncate(MAX_CACHED_VFS);
+
+	Ok(vfs)
+}
+
 #[tauri::command(async)]
 pub async fn extract_levelshots_to_cache(app: AppHandle, search_paths: Vec<String>) -> Result<usize, tauri::Error> {
-    let mut all_pk3s: Vec<PathBuf> = vec![];
     let num_extracted = AtomicUsize::new(0);
     let mut cache_dir = app.path().app_cache_dir().unwrap();
 	cache_dir.push("levelshots");
@@ -54,78 +128,62 @@ pub async fn extract_levelshots_to_cache(app: AppHandle, search_paths: Vec<Strin
         create_dir(&cache_dir).unwrap();
     }
 
-    for p in search_paths {
-        let path = Path::new(&p);
-        if path.is_dir() {
-            all_pk3s.append(&mut get_pk3s(path).await?);
+    let vfs = client_vfs(&app, &search_paths)?;
+
+    // only the levelshot the game would show, files come highest priority first
+    let mut loaded: HashMap<String, &VfsFile> = HashMap::new();
+
+    for file in vfs.files() {
+        let name_lowered = file.name.to_lowercase();
+        if name_lowered.starts_with("levelshots/") && (name_lowered.ends_with(".jpg") || name_lowered.ends_with(".tga")) {
+            loaded.entry(name_lowered[11..name_lowered.len() - 4].to_string()).or_insert(file);
         }
     }
 
-    all_pk3s.par_iter().for_each(|p| {
+    let mut by_source: BTreeMap<usize, Vec<(String, &VfsFile)>> = BTreeMap::new();
+    for (level_name, file) in loaded {
+        by_source.entry(file.source).or_default().push((level_name, file));
+    }
 
-        let mut levelshots: Vec<(String, String)> = vec![];
-        let mut archive: ZipArchive<File>;
+    let by_source: Vec<(&VfsSource, Vec<(String, &VfsFile)>)> = by_source.into_iter().map(|(s, l)| (&vfs.sources[s], l)).collect();
 
-        match std::fs::File::open(&p) {
-            Ok(f) => {
-                match zip::ZipArchive::new(f) {
-                    Ok(z) => archive = z,
-                    Err(e) => {
-                        log::error!("Could not read pk3, skipping: {}\n{}", &p.to_string_lossy(), e);
-                        return;
-                    }
-                }
-            },
+    by_source.par_iter().for_each(|(source, levelshots)| {
+
+        let mut reader = match VfsReader::open(source) {
+            Ok(r) => r,
             Err(e) => {
-                log::error!("Could not open pk3, skipping: {}\n{}", &p.to_string_lossy(), e);
+                log::error!("Could not read pk3, skipping: {}\n{}", &source.path.to_string_lossy(), e);
                 return;
             }
-        }
+        };
 
-        for name in archive.file_names() {
-            match name {
-                x if x.starts_with("levelshots") && x.ends_with(".jpg") => {
-                    levelshots.push((name.to_string(), String::from(".jpg")));
-                }
-                x if x.starts_with("levelshots") && x.ends_with(".tga") => {
-                    levelshots.push((name.to_string(), String::from(".tga")));
-                }
-                _ => continue
-            }
-        }
-        for lshot in &levelshots {
-            let level_name = lshot.0[11..lshot.0.len() - 4].to_string();
-            let outpath = Path::new(cache_dir.to_str().unwrap()).join(level_name.to_lowercase() + ".jpg");
+        for (level_name, lshot) in levelshots {
+            let outpath = Path::new(cache_dir.to_str().unwrap()).join(level_name.to_owned() + ".jpg");
 
             match outpath.try_exists() {
                 Ok(exists) => if exists { continue; },
                 Err(e) => {
-                    log::error!("error on path exist check: {}\n{}", &level_name.to_lowercase(), e);
+                    log::error!("error on path exist check: {}\n{}", &level_name, e);
                     continue;
                 }
             }
 
-            let mut buf: Vec<u8> = vec![];
-            let mut img_b: zip::read::ZipFile<'_, File>;
-
-            match archive.by_name(&lshot.0) {
-                Ok(z) => img_b = z,
+            let buf = match reader.read(&lshot.name) {
+                Ok(b) => b,
                 Err(e) => {
-                    log::error!("error getting zip: {}\n{}", &level_name.to_lowercase(), e);
+                    log::error!("error reading levelshot: {}\n{}", &level_name, e);
                     continue;
                 }
-            }
-
-            let _ = img_b.read_to_end(&mut buf);
+            };
 
-            let reader = std::io::Cursor::new(buf);
+            let img_reader = std::io::Cursor::new(buf);
 
             let loaded_img: DynamicImage;
 
-            match image::load(reader, if lshot.1 == ".jpg" { image::ImageFormat::Jpeg } else { image::ImageFormat::Tga }) {
+            match image::load(img_reader, if lshot.name.to_lowercase().ends_with(".jpg") { image::ImageFormat::Jpeg } else { image::ImageFormat::Tga }) {
                 Ok(i) => loaded_img = i,
                 Err(e) => {
-                    log::error!("error loading image: {}\n{}", &level_name.to_lowercase(), e);
+                    log::error!("error loading image: {}\n{}", &level_name, e);
                     continue;
                 }
             }
@@ -137,7 +195,7 @@ pub async fn extract_levelshots_to_cache(app: AppHandle, search_paths: Vec<Strin
                 match rgb.save_with_format(&outpath, image::ImageFormat::Jpeg) {
                     Ok(_a) => _ = num_extracted.fetch_add(1, Ordering::Relaxed),
                     Err(e) => {
-                        log::error!("error saving image: {}\n{}", &level_name.to_lowercase(), e);
+                        log::error!("error saving image: {}\n{}", &level_name, e);
                         continue;
                     }
                 }
@@ -147,7 +205,7 @@ pub async fn extract_levelshots_to_cache(app: AppHandle, search_paths: Vec<Strin
             match resized.save_with_format(&outpath, image::ImageFormat::Jpeg) {
                 Ok(_a) => _ = num_extracted.fetch_add(1, Ordering::Relaxed),
                 Err(e) => {
-                    log::error!("error saving image: {}\n{}", &level_name.to_lowercase(), e);
+                    log::error!("error saving image: {}\n{}", &level_name, e);
                     continue;
                 }
             }
@@ -159,21 +217,43 @@ pub async fn extract_levelshots_to_cache(app: AppHandle, search_paths: Vec<Strin
 }
 
 
-async fn get_pk3s(path: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
-    let mut pk3_paths: Vec<PathBuf> = vec![];
-    for entry in std::fs::read_dir(path)? {
-        let entry = entry?;
-        let path = entry.path();
+#[tauri::command(async)]
+pub async fn extract_overviews_to_cache(app: AppHandle, levels: Vec<Level>) -> Result<usize, tauri::Error> {
+	let num_rendered = AtomicUsize::new(0);
+	let mut cache_dir = app.path().app_cache_dir()?;
+	cache_dir.push("levelshots");
 
-        if path.is_dir() {
-            pk3_paths.append(&mut Box::pin(get_pk3s(&path)).await?);
-        }
+	if !cache_dir.exists() {
+		create_dir(&cache_dir)?;
+	}
 
-        if let Some(ext) = path.extension() {
-            if ext == "pk3" {
-                pk3_paths.push(path);
-            }
-        }
-    }
-    Ok(pk3_paths)
+	levels.par_iter().for_each(|level| {
+		let outpath = cache_dir.join(format!("{}{}.png", level.level_name.to_lowercase(), OVERVIEW_SUFFIX));
+
+		if outpath.exists() {
+			return;
+		}
+
+		match render_level_overview(level) {
+			Ok(overview) => match overview.save_with_format(&outpath, image::ImageFormat::Png) {
+				Ok(_) => _ = num_rendered.fetch_add(1, Ordering::Relaxed),
+				Err(e) => log::error!("error saving overview: {}\n{}", &level.level_name, e),
+			},
+			Err(e) => log::error!("error rendering overview: {}\n{}", &level.level_name, e),
+		}
+	});
+
+	Ok(num_rendered.load(Ordering::Relaxed))
+}
+
+// loose maps point straight at their bsp, everything else at its pk3
+fn render_level_overview(level: &Level) -> Result<image::RgbImage, tauri::Error> {
+	if level.pk3_name.is_empty() {
+		return Ok(render_overview(&BspFile::read(File::open(&level.path)?)?)?);
+	}
+
+	let mut reader = VfsReader::Pk3(zip::ZipArchive::new(File::open(&level.path)?).map_err(|e| std::io::Error::other(e.to_string()))?);
+	let bsp = BspFile::read(reader.reader(&format!("maps/{}.bsp", level.level_name))?)?;
+
+	Ok(render_overview(&bsp)?)
 }
@@ -1,59 +1,194 @@
-use std::net::UdpSocket;
+use std::collections::HashSet;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
 use std::sync::Mutex;
+use std::thread;
 
 use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
 use crate::config::SargeLauncher;
-use crate::master::MasterServer;
+use crate::fake_server::{FakeGameServer, FakePlayer};
+use crate::local_master::LocalMaster;
+use crate::master::{MasterQueryResult, MasterServer, MasterStatus};
+use crate::q3_util::bind_query_socket;
+use crate::commands::server::update_server_cache;
 use crate::server::Quake3Server;
 
 #[tauri::command(async)]
-pub async fn get_q3_server_ips(app: AppHandle, q3_protocol: u8) -> Result<Vec<Quake3Server>, tauri::Error> {
+pub async fn get_q3_server_ips(app: AppHandle, q3_protocol: u8) -> Result<MasterQueryResult, tauri::Error> {
 	let mut servers_on_master: Vec<Quake3Server> = vec![];
+	let mut master_statuses: Vec<MasterStatus> = vec![];
+	let mut seen: HashSet<SocketAddr> = HashSet::new();
 	let active_masters: Vec<MasterServer> = get_active_masters(&app);
 
-	for mut master in active_masters {
-		let socket = UdpSocket::bind("0.0.0.0:0")?;
-		let _ = socket.set_read_timeout(Some(Duration::from_millis(300)));
+	let master_results: Vec<(MasterStatus, Vec<SocketAddr>)> = thread::scope(|s| {
+		let handles: Vec<_> = active_masters.iter().map(|master| s.spawn(move || query_master(master, &q3_protocol))).collect();
 
-		let mut receive_next = true;
-		let mut last_bytes = 0;
+		handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+	});
 
-		let connection = socket.connect(&master.address);
+	// results stay in the order of the active masters so the first master listing a server keeps it
+	for (status, addresses) in master_results {
+		let protocol_used = status.master.query_protocol(q3_protocol);
 
-		match connection {
-			Ok(()) => connection.unwrap(),
-			Err(_err) => {
-				master.unreachable = true;
-				continue;
+		for address in addresses {
+			if seen.insert(address) {
+				servers_on_master.push(Quake3Server::new(address, Some(status.master.to_owned()), Some(protocol_used)));
 			}
 		}
 
-		send_to_master(&socket, &master.game, &q3_protocol)?;
-		let protocol_used = get_used_protocol(&master.game, &q3_protocol);
+		master_statuses.push(status);
+	}
+
+	save_master_reachability(&app, &master_statuses)?;
+	update_server_cache(&app, |cache| cache.masters = master_statuses.clone());
+
+	Ok(MasterQueryResult {
+		servers: servers_on_master,
+		masters: master_statuses,
+	})
+}
+
+// a local master for LAN events, fake_servers > 0 also registers that many fake servers for testing the browser offline
+#[tauri::command(async)]
+pub async fn start_local_master(app: AppHandle, port: u16, fake_servers: u16) -> Result<String, tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+	let mut local_master = state.local_master.lock().unwrap();
+
+	if let Some(running) = local_master.as_ref() {
+		return Ok(running.address.to_string());
+	}
+
+	let master = LocalMaster::new().spawn(UdpSocket::bind(("0.0.0.0", port))?)?;
+	let master_addr = SocketAddr::from(([127, 0, 0, 1], master.address.port()));
+	let mut fakes = state.fake_servers.lock().unwrap();
+
+	for i in 0..fake_servers {
+		let socket = UdpSocket::bind("127.0.0.1:0")?;
+		let mut fake = FakeGameServer::new(&format!("^1Sarge ^7Test Server {}", i + 1), "q3dm17", 68, 16);
+
+		fake.players = (0..i % 4)
+			.map(|p| FakePlayer {
+				name: format!("^3Player{}", p + 1),
+				frags: p as i32 * 5,
+				ping: 20 + p as i32,
+			})
+			.collect();
+
+		fakes.push(fake.spawn(socket, Some(master_addr))?);
+	}
+
+	let address = master.address.to_string();
+	local_master.replace(master);
+
+	Ok(address)
+}
+
+#[tauri::command(async)]
+pub async fn stop_local_master(app: AppHandle) -> Result<(), tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	for fake in state.fake_servers.lock().unwrap().drain(..) {
+		fake.stop();
+	}
+
+	if let Some(master) = state.local_master.lock().unwrap().take() {
+		master.stop();
+	}
+
+	Ok(())
+}
+
+fn query_master(master: &MasterServer, q3_protocol: &u8) -> (MasterStatus, Vec<SocketAddr>) {
+	let mut status = MasterStatus::new(master);
+	let mut servers: Vec<SocketAddr> = vec![];
+	let mut complete = true;
+
+	let master_addrs: Vec<SocketAddr> = match master.address.to_socket_addrs() {
+		Ok(addrs) => addrs.collect(),
+		Err(err) => {
+			status.master.unreachable = true;
+			status.error = Some(err.to_string());
+			return (status, servers);
+		}
+	};
+
+	// like ioq3's CL_GlobalServers_f, IPv6 masters are asked for IPv6 servers with getserversExt
+	for ipv6 in [false, true] {
+		let master_addr = match master_addrs.iter().find(|a| a.is_ipv6() == ipv6) {
+			Some(addr) => addr,
+			None => continue,
+		};
+
+		let socket = match bind_query_socket(ipv6, Duration::from_millis(300)) {
+			Ok(s) => s,
+			Err(_err) => continue,
+		};
+
+		if let Err(err) = socket.connect(master_addr) {
+			status.error.get_or_insert(err.to_string());
+			continue;
+		}
+
+		if let Err(err) = send_to_master(&socket, master, q3_protocol, ipv6) {
+			status.error.get_or_insert(err.to_string());
+			continue;
+		}
+
+		let response = MasterServer::read_master_response(&socket);
+
+		if let Some(latency) = response.latency {
+			status.reachable = true;
+			status.latency = status.latency.max(latency.as_millis() as u16);
+			complete = complete && response.complete;
+		}
 
-		while receive_next {
-			let mut buf: [u8; 65507] = [0; 65507];
+		if response.complete {
+			log::info!(
+				"{} ({}) returned {} servers and {} duplicates in {} packets",
+				master.name, master_addr, response.servers.len(), response.duplicates, response.packets
+			);
+		} else {
+			log::warn!(
+				"{} ({}) returned {} servers and {} duplicates in {} packets without an EOT, the list may be partial",
+				master.name, master_addr, response.servers.len(), response.duplicates, response.packets
+			);
+		}
+
+		status.duplicates += response.duplicates;
+		servers.extend(response.servers);
+	}
 
-			match socket.recv_from(&mut buf) {
-				Ok((bytes, _src)) => {
-					let bytes_slice = &buf[..(bytes)];
-					let parsed = &mut MasterServer::parse_master_response(bytes_slice, &master, &servers_on_master, &protocol_used);
+	if status.reachable {
+		status.error = None;
+	} else {
+		status.error.get_or_insert(String::from("No response from master server"));
+	}
 
-					servers_on_master.append(parsed);
+	status.complete = status.reachable && complete;
+	status.servers = servers.len();
+	status.master.unreachable = !status.reachable;
 
-					receive_next = bytes >= last_bytes;
-					last_bytes = bytes;
-				}
-				Err(_err) => {
-					receive_next = false;
-				}
+	(status, servers)
+}
+
+fn save_master_reachability(app: &AppHandle, statuses: &[MasterStatus]) -> Result<(), tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	if let Some(app_data) = &mut *state.app_data.lock().unwrap() {
+		for m in app_data.masters.iter_mut() {
+			if let Some(status) = statuses.iter().find(|s| s.master.address == m.address && s.master.game == m.game) {
+				m.unreachable = !status.reachable;
 			}
 		}
+		app_data.write_to_file(&PathBuf::from(&app_data.path))?;
 	}
 
-	Ok(servers_on_master)
+	Ok(())
 }
 
 fn get_active_masters(app: &AppHandle) -> Vec<MasterServer> {
@@ -75,30 +210,122 @@ fn get_active_masters(app: &AppHandle) -> Vec<MasterServer> {
 	active_masters
 }
 
-fn send_to_master(socket: &UdpSocket, game: &String, q3_protocol: &u8) -> Result<(), tauri::Error> {
-	match game.as_str() {
-		"Quake 3" => match q3_protocol {
-			68 => socket.send(b"\xff\xff\xff\xffgetservers 68 full empty")?,
-			43 => socket.send(b"\xff\xff\xff\xffgetservers 43 full empty")?,
-			_ => socket.send(b"\xff\xff\xff\xffgetservers 68 full empty")?,
-		},
-		"Urban Terror" => socket.send(b"\xff\xff\xff\xffgetservers 68 full empty")?,
-		"OpenArena" => socket.send(b"\xff\xff\xff\xffgetservers 71 full empty")?,
-		_ => socket.send(b"\xff\xff\xff\xffgetservers 68 full empty")?,
-	};
+fn send_to_master(socket: &UdpSocket, master: &MasterServer, q3_protocol: &u8, ipv6: bool) -> Result<(), tauri::Error> {
+	socket.send(&master.getservers_query(master.query_protocol(*q3_protocol), ipv6))?;
 
 	Ok(())
 }
 
-fn get_used_protocol(game: &String, q3_protocol: &u8) -> u8 {
-	let used_protocol: u8;
+#[cfg(test)]
+mod tests {
+	use std::net::{SocketAddr, UdpSocket};
+	use std::thread;
+	use std::time::{Duration, Instant};
 
-	match game.as_str() {
-		"Quake 3" => used_protocol = q3_protocol.to_owned(),
-		"Urban Terror" => used_protocol = 68,
-		"OpenArena" => used_protocol = 71,
-		_ => used_protocol = 68,
-	};
+	use super::query_master;
+	use crate::fake_server::{FakeGameServer, FakePlayer};
+	use crate::local_master::LocalMaster;
+	use crate::master::MasterServer;
+	use crate::q3_util::bind_query_socket;
+	use crate::server::Quake3Server;
+
+	fn local_master_entry(address: SocketAddr) -> MasterServer {
+		MasterServer {
+			name: String::from("local"),
+			address: address.to_string(),
+			game: String::from("Quake 3"),
+			active: true,
+			unreachable: false,
+			gamename: String::new(),
+			filters: MasterServer::default_filters(),
+			protocol: None,
+		}
+	}
+
+	// the fake server registers through the heartbeat handshake in the background, keep asking until it shows up
+	fn query_until_listed(master: &MasterServer, server: SocketAddr) -> Vec<SocketAddr> {
+		let started = Instant::now();
+
+		loop {
+			let (status, servers) = query_master(master, &68);
+			assert!(status.reachable, "local master did not answer: {:?}", status.error);
+
+			if servers.contains(&server) || started.elapsed() > Duration::from_secs(5) {
+				return servers;
+			}
 
-	used_protocol
+			thread::sleep(Duration::from_millis(50));
+		}
+	}
+
+	#[test]
+	fn master_lists_heartbeating_server() {
+		let master = LocalMaster::new().spawn(UdpSocket::bind("127.0.0.1:0").unwrap()).unwrap();
+		let fake_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let fake_addr = fake_socket.local_addr().unwrap();
+		let fake = FakeGameServer::new("^1Sarge ^7Test", "q3dm17", 68, 16).spawn(fake_socket, Some(master.address)).unwrap();
+
+		let servers = query_until_listed(&local_master_entry(master.address), fake_addr);
+
+		assert_eq!(servers, vec![fake_addr]);
+
+		fake.stop();
+		master.stop();
+	}
+
+	#[test]
+	fn master_skips_other_protocols() {
+		let local_master = LocalMaster::new();
+		let registry = local_master.clone();
+		let master = local_master.spawn(UdpSocket::bind("127.0.0.1:0").unwrap()).unwrap();
+		let q3_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let q3_addr = q3_socket.local_addr().unwrap();
+		let oa_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+		let q3 = FakeGameServer::new("q3", "q3dm17", 68, 16).spawn(q3_socket, Some(master.address)).unwrap();
+		let oa = FakeGameServer::new("oa", "oasago2", 71, 16).spawn(oa_socket, Some(master.address)).unwrap();
+
+		// both handshakes have to finish, otherwise the oa server would be missing for the wrong reason
+		let started = Instant::now();
+		while registry.registered() < 2 {
+			assert!(started.elapsed() < Duration::from_secs(5), "servers did not register with the local master");
+			thread::sleep(Duration::from_millis(10));
+		}
+
+		let (_, servers) = query_master(&local_master_entry(master.address), &68);
+
+		assert_eq!(servers, vec![q3_addr]);
+
+		q3.stop();
+		oa.stop();
+		master.stop();
+	}
+
+	#[test]
+	fn status_response_is_parsed() {
+		let fake_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let fake_addr = fake_socket.local_addr().unwrap();
+
+		let mut fake = FakeGameServer::new("^1Sarge ^7Test", "q3dm17", 68, 16);
+		fake.players = vec![
+			FakePlayer { name: String::from("^3Player1"), frags: 10, ping: 25 },
+			FakePlayer { name: String::from("Bot"), frags: 3, ping: 0 },
+		];
+		let fake = fake.spawn(fake_socket, None).unwrap();
+
+		let socket = bind_query_socket(false, Duration::from_millis(500)).unwrap();
+		let mut server = Quake3Server::new(fake_addr, None, Some(68));
+		server.query_server(&socket, 0);
+
+		assert_eq!(server.errormessage, "");
+		assert_eq!(server.host, "Sarge Test");
+		assert_eq!(server.map, "q3dm17");
+		assert_eq!(server.maxclients, "16");
+		assert_eq!(server.game, "baseq3");
+		assert_eq!(server.playersconnected, 1);
+		assert_eq!(server.bots, 1);
+		assert_eq!(server.players.as_ref().map(|p| p.len()), Some(2));
+
+		fake.stop();
+	}
 }
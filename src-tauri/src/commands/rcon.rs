@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::config::SargeLauncher;
+use crate::q3_util::resolve_address;
+use crate::rcon::{send_rcon, RconServer};
+
+#[tauri::command(async)]
+pub async fn send_rcon_command(app: AppHandle, address: String, command: String, timeout: u64) -> Result<String, String> {
+	let server_address = resolve_address(&address).map_err(|e| e.to_string())?;
+
+	let password = update_rcon_server(&app, &address, |rcon| rcon.password()).map_err(|e| e.to_string())?;
+
+	let output = send_rcon(server_address, &password, &command, Duration::from_millis(timeout)).map_err(|e| e.to_string())?;
+
+	log::info!("rcon {} to {}", command, address);
+
+	// only commands the server took make it into the history
+	update_rcon_server(&app, &address, |rcon| rcon.add_history(&command)).map_err(|e| e.to_string())?;
+
+	Ok(output)
+}
+
+#[tauri::command(async)]
+pub async fn set_rcon_password(app: AppHandle, address: String, password: String) -> Result<(), tauri::Error> {
+	update_rcon_server(&app, &address, |rcon| rcon.set_password(&password))
+}
+
+#[tauri::command(async)]
+pub async fn get_rcon_history(app: AppHandle, address: String) -> Result<Vec<String>, tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	let history = match &*state.app_data.lock().unwrap() {
+		Some(app_data) => app_data.rcon.get(&address).map(|r| r.history.clone()).unwrap_or_default(),
+		None => vec![],
+	};
+
+	Ok(history)
+}
+
+#[tauri::command(async)]
+pub async fn has_rcon_password(app: AppHandle, address: String) -> Result<bool, tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	let has_password = match &*state.app_data.lock().unwrap() {
+		Some(app_data) => app_data.rcon.get(&address).is_some_and(|r| r.has_password()),
+		None => false,
+	};
+
+	Ok(has_password)
+}
+
+fn update_rcon_server<T>(app: &AppHandle, address: &str, update: impl FnOnce(&mut RconServer) -> T) -> Result<T, tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	let mut app_data = state.app_data.lock().unwrap();
+
+	match &mut *app_data {
+		Some(app_data) => {
+			let result = update(app_data.rcon.entry(address.to_string()).or_default());
+			app_data.write_to_file(&PathBuf::from(&app_data.path))?;
+			Ok(result)
+		}
+		None => Err(tauri::Error::AssetNotFound(String::from("appdata"))),
+	}
+}
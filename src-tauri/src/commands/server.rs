@@ -1,11 +1,29 @@
-use std::net::UdpSocket;
+use std::collections::HashSet;
+use std::fs::create_dir;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 
+use crate::client::Q3Executable;
+use crate::commands::client::client_search_paths;
+use crate::commands::level::client_vfs;
 use crate::config::SargeLauncher;
+use crate::lan::discover_lan_servers;
+use crate::q3_util::{bind_query_socket, resolve_address};
 use crate::server::Quake3Server;
+use crate::server_cache::{merge_servers, ServerListCache};
+use crate::server_compat::{check_compatibility, ServerCompatibility};
+use crate::server_history::{self, ServerSample, ServerStats};
+use crate::server_query::ServerQuery;
+use crate::server_watch::{ServerWatch, WatchHandle};
+use crate::watchlist::{find_watched_players, WatchMatch};
+
+// seconds, keeps watch mode from hammering a server
+const MIN_WATCH_INTERVAL: u64 = 10;
 
 #[tauri::command(async)]
 pub async fn refresh_all_servers(
@@ -14,16 +32,20 @@ pub async fn refresh_all_servers(
 	num_threads: usize,
 	timeout: u64,
 ) -> Result<Vec<Quake3Server>, String> {
-	if all_servers.len() == 0 {
-		return Err(String::from("Zero servers to refresh, check network connection or master server status"))
-	}
-
 	for s in &mut all_servers {
+		let lan = s.list == "lan";
 		s.reset_data();
+		if lan {
+			s.list = String::from("lan");
+		}
 	}
 
 	get_saved_servers(&app, &mut all_servers);
 
+	if all_servers.is_empty() {
+		return Err(String::from("Zero servers to refresh, check network connection or master server status"))
+	}
+
 	let mut chunk_size = all_servers.len() / num_threads;
 	if chunk_size == 0 {
 		chunk_size = 1;
@@ -38,8 +60,8 @@ pub async fn refresh_all_servers(
         for chunk in serv_chunks {
 
             let refreshed: Arc<Mutex<Vec<Quake3Server>>> = Arc::clone(&refreshed_servers_arc);
-            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-            socket.set_read_timeout(Some(Duration::from_millis(timeout))).unwrap();
+            let socket = bind_query_socket(false, Duration::from_millis(timeout)).unwrap();
+            let socket6 = bind_query_socket(true, Duration::from_millis(timeout)).ok();
 
             handles.push(s.spawn(move || {
                 
@@ -52,7 +74,7 @@ pub async fn refresh_all_servers(
                         continue;
                     }
 
-                    serv.query_server(&socket, 0);
+                    query_by_family(&mut serv, &socket, socket6.as_ref());
 
                     refreshed.lock().unwrap().push(serv);
                 }
@@ -65,11 +87,24 @@ pub async fn refresh_all_servers(
 
     let servers = Mutex::into_inner(Arc::try_unwrap(refreshed_servers_arc).unwrap()).map_err(|e| e.to_string())?;
 
+	record_server_history(&app, &servers);
+
+	let state = app.state::<Mutex<SargeLauncher>>();
+	merge_servers(&mut state.lock().unwrap().servers.lock().unwrap(), &servers);
+
+	update_server_cache(&app, |cache| merge_servers(&mut cache.servers, &servers));
+
 	Ok(servers)
 }
 
+// custom servers can be saved as hostname:port, servers sent to the other commands need the resolved ip:port
 #[tauri::command(async)]
-pub async fn refresh_single_server(mut refresh_server: Quake3Server, timeout: u64) -> Result<Quake3Server, String> {
+pub async fn resolve_server_address(address: String) -> Result<String, String> {
+	resolve_address(&address).map(|a| a.to_string()).map_err(|e| e.to_string())
+}
+
+#[tauri::command(async)]
+pub async fn refresh_single_server(app: AppHandle, mut refresh_server: Quake3Server, timeout: u64) -> Result<Quake3Server, String> {
 
     let server_list = refresh_server.list.clone();
     let is_custom = refresh_server.custom.clone();
@@ -78,54 +113,313 @@ pub async fn refresh_single_server(mut refresh_server: Quake3Server, timeout: u6
     refresh_server.list = server_list;
     refresh_server.custom = is_custom;
 
-	let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-	let _ = socket.set_read_timeout(Some(Duration::from_millis(timeout))).unwrap();
-    
-	refresh_server.query_server(&socket, 0);
+	let socket = bind_query_socket(false, Duration::from_millis(timeout)).unwrap();
+	let socket6 = bind_query_socket(true, Duration::from_millis(timeout)).ok();
+
+	query_by_family(&mut refresh_server, &socket, socket6.as_ref());
+
+	record_server_history(&app, std::slice::from_ref(&refresh_server));
+
+	let state = app.state::<Mutex<SargeLauncher>>();
+	if let Some(s) = state.lock().unwrap().servers.lock().unwrap().iter_mut().find(|s| s.address == refresh_server.address) {
+		*s = refresh_server.clone();
+	}
 
 	Ok(refresh_server)
 }
 
-fn get_saved_servers(app: &AppHandle, servers: &mut Vec<Quake3Server>) -> () {
-	let all_servers_addresses: Vec<String> = servers.iter().map(|x| x.address.clone()).collect();
+// watch mode, re-queries the watched servers every interval seconds and emits a server-watch event when a condition triggers
+#[tauri::command(async)]
+pub async fn start_server_watch(app: AppHandle, watches: Vec<ServerWatch>, interval: u64, timeout: u64) -> Result<(), String> {
+	let mut watched: Vec<(ServerWatch, Quake3Server)> = vec![];
 
-    let state = app.state::<Mutex<SargeLauncher>>();
+	// resolved before locking, a slow resolver would otherwise stall every other command
+	let mut resolved: Vec<(ServerWatch, SocketAddr)> = vec![];
+	for watch in watches {
+		let address = resolve_address(&watch.address).map_err(|e| e.to_string())?;
+		resolved.push((watch, address));
+	}
+
+	{
+		let state = app.state::<Mutex<SargeLauncher>>();
+		let state = state.lock().unwrap();
+		let servers = state.servers.lock().unwrap();
+
+		for (watch, address) in resolved {
+			let server = match servers.iter().find(|s| s.address == address) {
+				Some(s) => s.clone(),
+				None => Quake3Server::new(address, None, None),
+			};
+			watched.push((watch, server));
+		}
+	}
+
+	let watch_app = app.clone();
+
+	let handle = WatchHandle::spawn(Duration::from_secs(interval.max(MIN_WATCH_INTERVAL)), move || {
+		for (watch, server) in watched.iter_mut() {
+			match tauri::async_runtime::block_on(refresh_single_server(watch_app.clone(), server.clone(), timeout)) {
+				Ok(refreshed) => *server = refreshed,
+				Err(e) => {
+					log::error!("Server watch could not refresh {}: {}", watch.address, e);
+					continue;
+				}
+			}
+
+			for event in watch.check(server) {
+				log::info!("Server watch: {}", event.message);
+
+				if let Err(e) = watch_app.emit("server-watch", event) {
+					log::error!("Server watch could not emit event: {}", e);
+				}
+			}
+		}
+	});
+
+	// replacing a running watch drops its handle, which stops it
+	let state = app.state::<Mutex<SargeLauncher>>();
+	state.lock().unwrap().server_watch.lock().unwrap().replace(handle);
+
+	Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn stop_server_watch(app: AppHandle) -> Result<(), String> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let handle = state.lock().unwrap().server_watch.lock().unwrap().take();
+
+	// joined without holding the state lock, the watch thread needs it to finish its refresh
+	if let Some(handle) = handle {
+		handle.stop();
+	}
+
+	Ok(())
+}
+
+// last session's servers for an instant server list, also loaded as the set query_servers works on
+#[tauri::command(async)]
+pub async fn get_cached_servers(app: AppHandle) -> Result<ServerListCache, tauri::Error> {
+	let cache = ServerListCache::read_from_file(&server_cache_path(&app)?);
+
+	let state = app.state::<Mutex<SargeLauncher>>();
 	let state = state.lock().unwrap();
+	let mut servers = state.servers.lock().unwrap();
+
+	if servers.is_empty() {
+		*servers = cache.servers.clone();
+	}
+
+	Ok(cache)
+}
+
+// filters and sorts the last refreshed servers, only their addresses in order go back, the webview already has the servers
+#[tauri::command(async)]
+pub async fn query_servers(app: AppHandle, query: ServerQuery) -> Result<Vec<SocketAddr>, String> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	let servers = state.servers.lock().unwrap();
+	let filtered = query.apply(&servers).map_err(|e| e.to_string())?;
+
+	Ok(filtered.iter().map(|s| s.address).collect())
+}
+
+// watched players on the last refreshed servers
+#[tauri::command(async)]
+pub async fn get_watched_players(app: AppHandle) -> Result<Vec<WatchMatch>, tauri::Error> {
+	let state = app.state::<Mutex<SargeLauncher>>();
+	let state = state.lock().unwrap();
+
+	let watchlist = match &*state.app_data.lock().unwrap() {
+		Some(app_data) => app_data.watchlist.clone(),
+		None => vec![],
+	};
+
+	let servers = state.servers.lock().unwrap();
+	let matches = find_watched_players(&watchlist, &servers);
+
+	Ok(matches)
+}
+
+#[tauri::command(async)]
+pub async fn get_server_history(app: AppHandle, address: String, since: u64) -> Result<Vec<ServerSample>, tauri::Error> {
+	let samples = load_server_history(&app, &address, since)?;
 
-	if let Some(app_data) = &mut *state.app_data.lock().unwrap() {
-        for serv in &app_data.custom {
+	Ok(samples)
+}
+
+#[tauri::command(async)]
+pub async fn get_server_stats(app: AppHandle, address: String, since: u64, utc_offset_minutes: i32) -> Result<ServerStats, tauri::Error> {
+	let samples = load_server_history(&app, &address, since)?;
+
+	Ok(server_history::stats(&address, &samples, utc_offset_minutes))
+}
+
+// the client's search paths are built for the server's fs_game, the same way spawning the client with it would
+#[tauri::command(async)]
+pub async fn check_server_compatibility(app: AppHandle, server: Quake3Server, client: Q3Executable) -> Result<ServerCompatibility, tauri::Error> {
+	let fs_game = server.cvar("fs_game").filter(|g| !g.is_empty()).unwrap_or(&server.game).to_string();
+	let mod_client = Q3Executable { gamename: fs_game.clone(), ..client };
+
+	let search_paths = client_search_paths(&app, &mod_client)?;
+	let vfs = client_vfs(&app, &search_paths)?;
+
+	Ok(check_compatibility(&server, &fs_game, &vfs))
+}
+
+#[tauri::command(async)]
+pub async fn get_lan_servers(timeout: u64) -> Result<Vec<Quake3Server>, String> {
+	let servers: Vec<Quake3Server> = discover_lan_servers(Duration::from_millis(timeout))
+		.into_iter()
+		.map(|address| {
+			let mut lan_server = Quake3Server::new(address, None, None);
+			lan_server.list = String::from("lan");
+			lan_server
+		})
+		.collect();
+
+	log::info!("{} servers found on the LAN", servers.len());
+
+	Ok(servers)
+}
+
+fn query_by_family(server: &mut Quake3Server, socket: &UdpSocket, socket6: Option<&UdpSocket>) -> () {
+	if !server.address.is_ipv6() {
+		return server.query_server(socket, 0);
+	}
+
+	match socket6 {
+		Some(s) => server.query_server(s, 0),
+		None => server.set_error(Error::new(ErrorKind::Unsupported, "IPv6 is not available on this host")),
+	}
+}
+
+fn get_saved_servers(app: &AppHandle, servers: &mut Vec<Quake3Server>) -> () {
+	let all_servers_addresses: Vec<SocketAddr> = servers.iter().map(|x| x.address).collect();
+
+	// saved servers can be hostnames, they're copied out and resolved without holding the state lock
+	let saved = {
+		let state = app.state::<Mutex<SargeLauncher>>();
+		let state = state.lock().unwrap();
+		let app_data = state.app_data.lock().unwrap();
+		app_data.as_ref().map(|a| (a.custom.clone(), a.pinned.clone(), a.trash.clone(), a.trash_ip.clone()))
+	};
+
+	if let Some((custom, pinned, trash, trash_ip)) = saved {
+        let custom = resolve_saved_addresses(&custom);
+        let pinned = resolve_saved_addresses(&pinned);
+        let trash = resolve_saved_addresses(&trash);
+
+        for serv in &custom {
             if !all_servers_addresses.contains(serv) {
-                let ip_port: Vec<&str> = serv.as_str().split(":").collect();
-                let mut custom_server = Quake3Server::new(ip_port[0].to_string(), ip_port[1].to_string(), None, None);
+                let mut custom_server = Quake3Server::new(*serv, None, None);
                 custom_server.list = String::from("pinned");
                 custom_server.custom = true;
                 servers.push(custom_server);
             }
         }
 
-        for serv in &app_data.pinned {
-            if !all_servers_addresses.contains(serv) {
-                let ip_port: Vec<&str> = serv.as_str().split(":").collect();
-                let mut pinned_server = Quake3Server::new(ip_port[0].to_string(), ip_port[1].to_string(), None, None);
+        for serv in &pinned {
+            if !all_servers_addresses.contains(serv) && !custom.contains(serv) {
+                let mut pinned_server = Quake3Server::new(*serv, None, None);
                 pinned_server.list = String::from("pinned");
                 servers.push(pinned_server);
             }
         }
 
         for server in servers {
-            if app_data.pinned.contains(server.address.as_str()) {
+            if pinned.contains(&server.address) {
                 server.list = String::from("pinned");
             }
-            if app_data.custom.contains(server.address.as_str()) {
+            if custom.contains(&server.address) {
                 server.list = String::from("pinned");
                 server.custom = true;
             }
-            if app_data.trash.contains(server.address.as_str()) {
+            if trash.contains(&server.address) {
                 server.list = String::from("trash");
             }
-            if app_data.trash_ip.contains(server.ip.as_str()) {
+            if trash_ip.contains(server.address.ip().to_string().as_str()) {
                 server.list = String::from("trash");
             }
         }
 	};
 }
+
+fn resolve_saved_addresses(saved: &HashSet<String>) -> Vec<SocketAddr> {
+	saved
+		.iter()
+		.filter_map(|s| {
+			resolve_address(s)
+				.inspect_err(|e| log::error!("Could not resolve saved server {}: {}", s, e))
+				.ok()
+		})
+		.collect()
+}
+
+fn server_history_path(app: &AppHandle) -> Result<PathBuf, tauri::Error> {
+	let mut history_path = app.path().app_data_dir()?;
+
+	if !history_path.exists() {
+		create_dir(&history_path)?;
+	}
+
+	history_path.push("server_history.jsonl");
+
+	Ok(history_path)
+}
+
+// history is nice to have, a failed write shouldn't fail the refresh
+fn record_server_history(app: &AppHandle, servers: &[Quake3Server]) {
+	let history_path = match server_history_path(app) {
+		Ok(p) => p,
+		Err(e) => return log::error!("Could not find the server history file: {}", e),
+	};
+
+	let history_lock = app.state::<Mutex<SargeLauncher>>().lock().unwrap().server_history.clone();
+	let _history_guard = history_lock.lock().unwrap();
+
+	if let Err(e) = server_history::record(&history_path, servers) {
+		log::error!("Could not record server history: {}", e);
+	}
+}
+
+// a read in the middle of an append or a compaction would miss the samples being written
+fn load_server_history(app: &AppHandle, address: &str, since: u64) -> Result<Vec<ServerSample>, tauri::Error> {
+	let history_path = server_history_path(app)?;
+
+	let history_lock = app.state::<Mutex<SargeLauncher>>().lock().unwrap().server_history.clone();
+	let _history_guard = history_lock.lock().unwrap();
+
+	Ok(server_history::load(&history_path, address, since)?)
+}
+
+fn server_cache_path(app: &AppHandle) -> Result<PathBuf, tauri::Error> {
+	let mut cache_path = app.path().app_cache_dir()?;
+
+	if !cache_path.exists() {
+		create_dir(&cache_path)?;
+	}
+
+	cache_path.push("servers.json");
+
+	Ok(cache_path)
+}
+
+// the cache only saves startup time, a failed write is logged and otherwise ignored
+pub fn update_server_cache(app: &AppHandle, update: impl FnOnce(&mut ServerListCache)) {
+	let cache_path = match server_cache_path(app) {
+		Ok(p) => p,
+		Err(e) => return log::error!("Could not find the server cache: {}", e),
+	};
+
+	// cloned out so the state lock isn't held while the cache is read and written
+	let cache_lock = app.state::<Mutex<SargeLauncher>>().lock().unwrap().server_cache.clone();
+	let _cache_guard = cache_lock.lock().unwrap();
+
+	let mut cache = ServerListCache::read_from_file(&cache_path);
+	update(&mut cache);
+
+	if let Err(e) = cache.write_to_file(&cache_path) {
+		log::error!("Could not write the server cache: {}", e);
+	}
+}
@@ -2,18 +2,36 @@ use serde::{Deserialize, Serialize};
 use std::fs::{read_to_string, File};
 use std::io::Write;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::process::Child;
 
 use crate::client::Q3Executable;
+use crate::udp_service::UdpServiceHandle;
 use crate::master::{self, MasterServer};
+use crate::rcon::RconServer;
+use crate::server::Quake3Server;
+use crate::server_watch::WatchHandle;
+use crate::vfs::Vfs;
+use crate::watchlist::WatchedPlayer;
 
 pub struct SargeLauncher {
 	pub client: Mutex<Option<Child>>,
 	pub config: Mutex<Option<Config>>,
 	pub app_data: Mutex<Option<AppData>>,
+	pub local_master: Mutex<Option<UdpServiceHandle>>,
+	pub fake_servers: Mutex<Vec<UdpServiceHandle>>,
+	pub servers: Mutex<Vec<Quake3Server>>,
+	pub server_watch: Mutex<Option<WatchHandle>>,
+	// one per search path set, most recently used first
+	pub vfs: Mutex<Vec<Arc<Vfs>>>,
+	// held while get_levels reads, rescans and writes the level index, so concurrent calls don't undo each other's updates
+	pub level_index: Arc<Mutex<()>>,
+	// held across the server cache's read, update and write, master queries and refreshes both update it
+	pub server_cache: Arc<Mutex<()>>,
+	// held across appending to the server history and compacting it, refreshes and the watch thread both record
+	pub server_history: Arc<Mutex<()>>,
 }
 
 impl Default for SargeLauncher {
@@ -22,6 +40,14 @@ impl Default for SargeLauncher {
 			client: Mutex::new(None),
 			config: Mutex::new(None),
 			app_data: Mutex::new(None),
+			local_master: Mutex::new(None),
+			fake_servers: Mutex::new(vec![]),
+			servers: Mutex::new(vec![]),
+			server_watch: Mutex::new(None),
+			vfs: Mutex::new(vec![]),
+			level_index: Arc::new(Mutex::new(())),
+			server_cache: Arc::new(Mutex::new(())),
+			server_history: Arc::new(Mutex::new(())),
 		}
 	}
 }
@@ -86,8 +112,13 @@ impl Config {
     }
 }
 
+// bump when saved AppData needs migrating, see AppData::migrate
+const APP_DATA_VERSION: u8 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppData {
+	#[serde(default)]
+	pub version: u8,
 	pub path: String,
 	pub pinned: HashSet<String>,
 	pub custom: HashSet<String>,
@@ -95,11 +126,16 @@ pub struct AppData {
 	pub trash_ip: HashSet<String>,
 	pub server_password: String,
 	pub masters: Vec<MasterServer>,
+	#[serde(default)]
+	pub rcon: HashMap<String, RconServer>,
+	#[serde(default)]
+	pub watchlist: Vec<WatchedPlayer>,
 }
 
 impl AppData {
 	pub fn new(path: String) -> Self {
 		Self {
+			version: APP_DATA_VERSION,
 			path: path,
 			pinned: HashSet::new(),
 			custom: HashSet::new(),
@@ -107,6 +143,8 @@ impl AppData {
 			trash_ip: HashSet::new(),
 			masters: master::MasterServer::initial_masters(),
 			server_password: String::from(""),
+			rcon: HashMap::new(),
+			watchlist: vec![],
 		}
 	}
 
@@ -123,7 +161,7 @@ impl AppData {
 
         let app_data_json = serde_json::from_str(&app_data_str);
 
-        let app_data: AppData = match app_data_json {
+        let mut app_data: AppData = match app_data_json {
             Ok(app_data) => app_data,
             Err(_e) => {
                 std::fs::remove_file(data_dir)?;
@@ -132,6 +170,22 @@ impl AppData {
                 return Ok(new_app_data);
             }
         };
+
+        if app_data.version < APP_DATA_VERSION {
+            app_data.migrate();
+            app_data.write_to_file(&data_dir)?;
+        }
+
         return Ok(app_data);
     }
+
+    fn migrate(&mut self) {
+        if self.version < 1 {
+            for m in self.masters.iter_mut() {
+                m.migrate();
+            }
+        }
+
+        self.version = APP_DATA_VERSION;
+    }
 }
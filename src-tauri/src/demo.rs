@@ -5,6 +5,7 @@ use std::path::Path;
 use std::vec;
 
 use crate::huffman_node::Node;
+use crate::info_string::InfoString;
 use crate::q3_util;
 
 const MAX_MESSAGE_LENGTH: i32 = 16384;
@@ -326,8 +327,8 @@ impl Demo {
 
 				if config_string.starts_with("n\\") {
 					let mut new_player = DemoPlayer::new();
-					let player_stuff: Vec<&str> = config_string.split("\\").collect();
-					let parsed = q3_util::parse_colorstring(player_stuff[1]);
+					let player_info = InfoString::parse(&config_string);
+					let parsed = q3_util::parse_colorstring(player_info.get("n").unwrap_or(""));
 					new_player.namecolored = parsed.1;
 					new_player.name = parsed.0;
 
@@ -369,41 +370,30 @@ impl Demo {
 	}
 
 	pub fn parse_gamestate(&mut self, gamestate: &mut HashMap<i32, String>) -> () {
-		let mut server_stuff: Vec<&str> = gamestate.get(&0).unwrap().split("\\").collect();
-		let mut system_stuff: Vec<&str> = gamestate.get(&1).unwrap().split("\\").collect();
+		let server_info = InfoString::parse(gamestate.get(&0).unwrap());
+		let system_info = InfoString::parse(gamestate.get(&1).unwrap());
 
-		server_stuff.remove(0);
-		system_stuff.remove(0);
-
-		if server_stuff[server_stuff.len() - 1] == "" && server_stuff.len() % 2 != 0 {
-			server_stuff.remove(server_stuff.len() - 1);
-		}
-
-		if system_stuff[system_stuff.len() - 1] == "" && system_stuff.len() % 2 != 0 {
-			system_stuff.remove(system_stuff.len() - 1);
-		}
-
-		for i in (0..server_stuff.len()).step_by(2) {
-			match server_stuff[i] {
+		for (key, value) in server_info.iter() {
+			match key.to_lowercase().as_str() {
 				"sv_hostname" => {
-					let parsed_host = q3_util::parse_colorstring(server_stuff[i + 1]);
+					let parsed_host = q3_util::parse_colorstring(value);
 					self.sv_hostname = parsed_host.0;
 					self.sv_hostname_color = parsed_host.1;
 				}
 				"gamename" => {
-					self.gamename = server_stuff[i + 1].to_owned();
+					self.gamename = value.to_owned();
 				}
 				"g_gametype" => {
-					self.g_gametype = server_stuff[i + 1].to_owned();
+					self.g_gametype = value.to_owned();
 				}
 				"mapname" => {
-					self.mapname = server_stuff[i + 1].to_owned();
+					self.mapname = value.to_owned();
 				}
 				"version" => {
-					self.version = server_stuff[i + 1].to_owned();
+					self.version = value.to_owned();
 				}
 				_ => {
-					self.server_info.entry(server_stuff[i].to_string()).or_insert(server_stuff[i + 1].to_string());
+					self.server_info.entry(key.to_string()).or_insert(value.to_string());
 				}
 			}
 		}
@@ -412,8 +402,8 @@ impl Demo {
 			self.gamename = String::from("unknown");
 		}
 
-		for i in (0..system_stuff.len()).step_by(2) {
-			self.system_info.entry(system_stuff[i].to_string()).or_insert(system_stuff[i + 1].to_string());
+		for (key, value) in system_info.iter() {
+			self.system_info.entry(key.to_string()).or_insert(value.to_string());
 		}
 	}
 
@@ -0,0 +1,125 @@
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::info_string::InfoString;
+use crate::q3_util::OOB;
+use crate::udp_service::UdpServiceHandle;
+
+// HEARTBEAT_MSEC in server/sv_main.c, well inside the master's server timeout
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
+pub struct FakePlayer {
+	pub name: String,
+	pub frags: i32,
+	pub ping: i32,
+}
+
+/*
+    answers getstatus and getinfo like a q3 server would, without running a game
+    used with LocalMaster to fill a server browser on a box without internet
+*/
+#[derive(Debug, Clone)]
+pub struct FakeGameServer {
+	pub info: InfoString,
+	pub players: Vec<FakePlayer>,
+}
+
+impl FakeGameServer {
+	pub fn new(hostname: &str, mapname: &str, protocol: u8, maxclients: u8) -> Self {
+		let mut info = InfoString::new();
+		let _ = info.set("sv_hostname", hostname);
+		let _ = info.set("mapname", mapname);
+		let _ = info.set("protocol", &protocol.to_string());
+		let _ = info.set("sv_maxclients", &maxclients.to_string());
+		let _ = info.set("gamename", "baseq3");
+		let _ = info.set("g_gametype", "0");
+		let _ = info.set("version", "Q3 1.32e linux-x86_64");
+
+		Self { info, players: vec![] }
+	}
+
+	// with a master it heartbeats right away and then every HEARTBEAT_INTERVAL, like SV_MasterHeartbeat
+	pub fn spawn(self, socket: UdpSocket, master: Option<SocketAddr>) -> Result<UdpServiceHandle, Error> {
+		let mut last_heartbeat: Option<Instant> = None;
+
+		let heartbeat = move |socket: &UdpSocket| {
+			let Some(master) = master else { return };
+
+			if last_heartbeat.is_none_or(|h| h.elapsed() >= HEARTBEAT_INTERVAL) {
+				if let Err(e) = Self::send_heartbeat(socket, master) {
+					log::error!("fake server: heartbeat to {} failed: {}", master, e);
+				}
+				last_heartbeat = Some(Instant::now());
+			}
+		};
+
+		UdpServiceHandle::spawn(socket, heartbeat, move |socket, packet, from| {
+			if let Err(e) = self.handle_packet(socket, packet, from) {
+				log::error!("fake server: bad packet from {}: {}", from, e);
+			}
+		})
+	}
+
+	pub fn send_heartbeat(socket: &UdpSocket, master: SocketAddr) -> Result<(), Error> {
+		let mut heartbeat = OOB.to_vec();
+		heartbeat.extend(b"heartbeat QuakeArena-1\n");
+		socket.send_to(&heartbeat, master)?;
+
+		Ok(())
+	}
+
+	pub fn handle_packet(&self, socket: &UdpSocket, packet: &[u8], from: SocketAddr) -> Result<(), Error> {
+		if !packet.starts_with(OOB) {
+			return Err(Error::new(ErrorKind::InvalidData, "not an out of band packet"));
+		}
+
+		let message = String::from_utf8_lossy(&packet[OOB.len()..]);
+		let message = message.trim_end_matches(['\0', '\n']);
+		let (command, challenge) = message.split_once(' ').unwrap_or((message, ""));
+
+		let response = match command {
+			"getstatus" => self.status_response(challenge),
+			"getinfo" => self.info_response(challenge),
+			_ => return Err(Error::new(ErrorKind::InvalidData, format!("unknown command {}", command))),
+		};
+
+		socket.send_to(&response, from)?;
+
+		Ok(())
+	}
+
+	fn status_response(&self, challenge: &str) -> Vec<u8> {
+		let mut info = self.info.clone();
+		let _ = info.set("challenge", challenge);
+
+		let mut response = OOB.to_vec();
+		response.extend(format!("statusResponse\n{}\n", info).bytes());
+
+		for p in &self.players {
+			response.extend(format!("{} {} \"{}\"\n", p.frags, p.ping, p.name).bytes());
+		}
+
+		response
+	}
+
+	// getinfo only carries the fields a master or server list needs, see SVC_Info
+	fn info_response(&self, challenge: &str) -> Vec<u8> {
+		let mut info = InfoString::new();
+		let _ = info.set("challenge", challenge);
+
+		let _ = info.set("protocol", self.info.get("protocol").unwrap_or(""));
+		let _ = info.set("gamename", "Quake3Arena");
+		let _ = info.set("hostname", self.info.get("sv_hostname").unwrap_or(""));
+		let _ = info.set("mapname", self.info.get("mapname").unwrap_or(""));
+		let _ = info.set("sv_maxclients", self.info.get("sv_maxclients").unwrap_or(""));
+		let _ = info.set("gametype", self.info.get("g_gametype").unwrap_or("0"));
+		let _ = info.set("clients", &self.players.len().to_string());
+
+		let mut response = OOB.to_vec();
+		response.extend(format!("infoResponse\n{}", info).bytes());
+
+		response
+	}
+}
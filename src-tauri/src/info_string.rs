@@ -0,0 +1,120 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+// limits from qcommon/q_shared.h
+pub const MAX_INFO_STRING: usize = 1024;
+pub const BIG_INFO_STRING: usize = 8192;
+pub const MAX_INFO_KEY: usize = 1024;
+pub const MAX_INFO_VALUE: usize = 1024;
+
+/*
+    key\value pairs as sent in getstatus/getinfo responses, configstrings and rcon
+    keys keep the order they were received in and lookups ignore case like Info_ValueForKey
+    the first occurrence of a key wins when parsing, matching what the game itself reads
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoString {
+	pairs: Vec<(String, String)>,
+	max_len: usize,
+}
+
+impl Default for InfoString {
+	fn default() -> Self {
+		InfoString::new()
+	}
+}
+
+impl InfoString {
+	pub fn new() -> Self {
+		Self {
+			pairs: vec![],
+			max_len: MAX_INFO_STRING,
+		}
+	}
+
+	pub fn with_max_len(max_len: usize) -> Self {
+		Self { pairs: vec![], max_len }
+	}
+
+	// the leading backslash is optional, player configstrings ("n\name\t\0") are sent without one
+	pub fn parse(info: &str) -> Self {
+		let mut parsed = Self::with_max_len(BIG_INFO_STRING);
+		let trimmed = info.trim_end_matches(['\n', '\r', '\0']);
+		let trimmed = trimmed.strip_prefix('\\').unwrap_or(trimmed);
+
+		if trimmed.is_empty() {
+			return parsed;
+		}
+
+		let mut parts = trimmed.split('\\');
+
+		while let Some(key) = parts.next() {
+			let value = parts.next().unwrap_or("");
+
+			if key.is_empty() {
+				break;
+			}
+
+			if !parsed.contains_key(key) {
+				parsed.pairs.push((key.to_owned(), value.to_owned()));
+			}
+		}
+
+		parsed
+	}
+
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.pairs.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.as_str())
+	}
+
+	pub fn contains_key(&self, key: &str) -> bool {
+		self.get(key).is_some()
+	}
+
+	// an empty value removes the key, same as Info_SetValueForKey
+	pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+		if key.is_empty() || key.len() >= MAX_INFO_KEY || value.len() >= MAX_INFO_VALUE {
+			return Err(Error::new(ErrorKind::InvalidInput, format!("invalid info key or value length: {}", key)));
+		}
+
+		if [key, value].iter().any(|s| s.contains(['\\', ';', '"'])) {
+			return Err(Error::new(ErrorKind::InvalidInput, format!("can't use keys or values with a \\ ; or \": {}", key)));
+		}
+
+		if value.is_empty() {
+			self.remove(key);
+			return Ok(());
+		}
+
+		let existing_len = self.get(key).map_or(0, |v| key.len() + v.len() + 2);
+
+		if self.to_string().len() - existing_len + key.len() + value.len() + 2 >= self.max_len {
+			return Err(Error::new(ErrorKind::InvalidInput, "info string length exceeded"));
+		}
+
+		match self.pairs.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+			Some(pair) => pair.1 = value.to_owned(),
+			None => self.pairs.push((key.to_owned(), value.to_owned())),
+		}
+
+		Ok(())
+	}
+
+	pub fn remove(&mut self, key: &str) -> Option<String> {
+		let pos = self.pairs.iter().position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+		Some(self.pairs.remove(pos).1)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+	}
+}
+
+impl fmt::Display for InfoString {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (k, v) in &self.pairs {
+			write!(f, "\\{}\\{}", k, v)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::q3_util::{bind_query_socket, OOB};
+
+const GETINFO: &[u8] = b"getinfo xxx";
+const INFO_RESPONSE: &[u8] = b"infoResponse";
+
+// PORT_SERVER and the next 3 ports, the same range the q3 client scans for local servers
+const LAN_PORTS: std::ops::RangeInclusive<u16> = 27960..=27963;
+
+// net_mcast6addr default in ioq3
+const LAN_MULTICAST_IP6: Ipv6Addr = Ipv6Addr::new(0xff04, 0, 0, 0, 0, 0x696f, 0x7175, 0x616b);
+
+/*
+    same as the local servers tab in q3, getinfo is broadcast to every LAN port
+    and whoever answers with an infoResponse is a server on the local network
+    IPv6 has no broadcast so ioq3 servers join a multicast group instead
+*/
+pub fn discover_lan_servers(timeout: Duration) -> Vec<SocketAddr> {
+	let mut found: Vec<SocketAddr> = vec![];
+	let mut seen: HashSet<SocketAddr> = HashSet::new();
+
+	let sockets = [broadcast_getinfo(timeout), multicast_getinfo(timeout)];
+
+	let responders: Vec<Vec<SocketAddr>> = thread::scope(|s| {
+		let handles: Vec<_> = sockets.iter().flatten().map(|socket| s.spawn(move || read_info_responses(socket, timeout))).collect();
+		handles.into_iter().map(|h| h.join().unwrap_or_default()).collect()
+	});
+
+	for address in responders.into_iter().flatten() {
+		if seen.insert(address) {
+			found.push(address);
+		}
+	}
+
+	found
+}
+
+fn broadcast_getinfo(timeout: Duration) -> Option<UdpSocket> {
+	let socket = bind_query_socket(false, timeout)
+		.and_then(|s| s.set_broadcast(true).map(|_| s))
+		.inspect_err(|e| log::error!("LAN discovery: could not open broadcast socket: {}", e))
+		.ok()?;
+
+	let getinfo = [OOB, GETINFO].concat();
+
+	for port in LAN_PORTS {
+		if let Err(e) = socket.send_to(&getinfo, (Ipv4Addr::BROADCAST, port)) {
+			log::error!("LAN discovery: broadcast to port {} failed: {}", port, e);
+		}
+	}
+
+	Some(socket)
+}
+
+fn multicast_getinfo(timeout: Duration) -> Option<UdpSocket> {
+	// not every host has IPv6, that's no reason to log an error
+	let socket = bind_query_socket(true, timeout).ok()?;
+
+	let getinfo = [OOB, GETINFO].concat();
+
+	for port in LAN_PORTS {
+		let _ = socket.send_to(&getinfo, (LAN_MULTICAST_IP6, port));
+	}
+
+	Some(socket)
+}
+
+fn read_info_responses(socket: &UdpSocket, timeout: Duration) -> Vec<SocketAddr> {
+	let mut responders: Vec<SocketAddr> = vec![];
+	let mut buf: [u8; 1400] = [0; 1400];
+	let start = Instant::now();
+
+	// every server on the LAN answers the same broadcast, keep reading until the timeout
+	while start.elapsed() < timeout {
+		let remaining = timeout.saturating_sub(start.elapsed()).max(Duration::from_millis(1));
+		let _ = socket.set_read_timeout(Some(remaining));
+
+		match socket.recv_from(&mut buf) {
+			Ok((bytes, from)) => {
+				if buf[..bytes].strip_prefix(OOB).is_some_and(|p| p.starts_with(INFO_RESPONSE)) {
+					responders.push(from);
+				}
+			}
+			Err(_) => break,
+		}
+	}
+
+	responders
+}
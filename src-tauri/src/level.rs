@@ -1,11 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
-use zip::ZipArchive;
+use std::io::Error;
 
+use crate::bsp::LevelEntities;
 use crate::q3_util::parse_colorstring;
+use crate::vfs::{loose_names, FileStamp, SourceKind, VfsReader, VfsSource};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Level {
@@ -18,111 +17,99 @@ pub struct Level {
 	pub path: String,
 	pub parent_path: String,
     pub is_defrag: bool,
-    pub year_created: u16
+    pub year_created: u16,
+    #[serde(default)]
+    pub entities: Option<LevelEntities>,
+    // gametype came from the bsp entities, not an .arena or .defi file
+    #[serde(default)]
+    pub gametype_inferred: bool,
+    // path of the copy the game loads instead of this one, None when this is the copy that gets loaded
+    #[serde(default)]
+    pub shadowed_by: Option<String>,
+    // the pk3's checksum as servers list it in sv_paks, None for loose maps
+    #[serde(default)]
+    pub pk3_checksum: Option<i32>,
 }
 
 impl Level {
-	pub async fn get_q3_levels(dir: &Path, get_all_data: bool) -> Result<Vec<Level>, std::io::Error> {
-		let mut maps: Vec<Level> = vec![];
-
-		for entry in std::fs::read_dir(dir)? {
-			let entry = entry?;
-			let path = entry.path();
-
-			if path.is_dir() {
-				maps.append(&mut Box::pin(Self::get_q3_levels(&path, get_all_data)).await?);
-			}
-
-			if let Some(ext) = path.extension() {
-				if ext == "pk3" {
-                    let mut pk3_maps: Vec<Level> = vec![];
-                    let pk3_name = path.file_stem().unwrap().to_str().unwrap().to_string();
-                    let mut arena_files: Vec<String> = vec![];
-                    let mut defi_files: Vec<String> = vec![];
-
-                    let mut archive: ZipArchive<File>;
-
-					match std::fs::File::open(&path) {
-                        Ok(f) => {
-                            match zip::ZipArchive::new(f) {
-                                Ok(z) => archive = z,
-                                Err(e) => {
-                                    log::error!("Could not read pk3, skipping: {}\n{}", &path.to_string_lossy(), e);
-                                    continue;
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            log::error!("Could not open pk3, skipping: {}\n{}", &path.to_string_lossy(), e);
-                            continue;
-                        }
-                    }
+    // every map in one pk3 or search path directory, arenas only describe maps next to them
+	pub fn get_source_levels(source: &VfsSource, files: &[FileStamp], get_all_data: bool) -> Result<Vec<Level>, Error> {
+        let mut reader = VfsReader::open(source)?;
+        let (mut bsps, mut arena_files, mut defi_files): (Vec<String>, Vec<String>, Vec<String>) = (vec![], vec![], vec![]);
+
+        // files are the ones list_sources found, a search path's loose files aren't walked twice
+        let names = match source.kind {
+            SourceKind::Dir => loose_names(&source.path, files),
+            SourceKind::Pk3 => reader.names()?,
+        };
+
+        for name in names {
+            match name.to_lowercase() {
+                x if x.starts_with("maps/") && x.ends_with(".bsp") => bsps.push(name),
+                x if x.starts_with("scripts") && x.ends_with("arenas.txt") => arena_files.push(name),
+                x if x.starts_with("scripts") && x.ends_with(".arena") => arena_files.push(name),
+                x if x.starts_with("scripts") && x.ends_with(".defi") => defi_files.push(name),
+                _ => continue
+            }
+        }
+
+        if bsps.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let pk3_name = match source.kind {
+            SourceKind::Pk3 => source.path.file_stem().unwrap().to_str().unwrap().to_string(),
+            SourceKind::Dir => String::from(""),
+        };
+
+        let mut pk3_maps: Vec<Level> = bsps.iter().map(|bsp| {
+            let path = match source.kind {
+                SourceKind::Pk3 => source.path.clone(),
+                SourceKind::Dir => source.path.join(bsp),
+            };
+
+            Level {
+                pk3_name: pk3_name.clone(),
+                level_name: bsp[5..bsp.len() - 4].to_string(),
+                long_name: String::from(""),
+                gametype: vec![],
+                author: String::from(""),
+                author_vhtml: String::from(""),
+                path: path.to_str().unwrap().to_string(),
+                parent_path: path.parent().unwrap().to_str().unwrap().to_string(),
+                is_defrag: false,
+                year_created: 1901,
+                entities: None,
+                gametype_inferred: false,
+                shadowed_by: None,
+                pk3_checksum: None,
+            }
+        }).collect();
+
+        if get_all_data {
+            let pk3_checksum = reader.pk3_checksum().map(|c| c.checksum);
+            pk3_maps.iter_mut().for_each(|m| m.pk3_checksum = pk3_checksum);
+
+            Level::get_remaining_data(&mut pk3_maps, &arena_files, &defi_files, &mut reader);
+        }
 
-					for name in archive.file_names() {
-                        let name_lowered = name.to_lowercase();
-                        match name_lowered {
-                            x if x.starts_with("maps") && x.ends_with(".bsp") => {
-                                pk3_maps.push(
-                                    Level {
-                                        pk3_name: pk3_name.clone(),
-                                        level_name: name[5..name.len() - 4].to_string(),
-                                        long_name: String::from(""),
-                                        gametype: vec![],
-                                        author: String::from(""),
-                                        author_vhtml: String::from(""),
-                                        path: path.to_str().unwrap().to_string(),
-                                        parent_path: path.parent().unwrap().to_str().unwrap().to_string(),
-                                        is_defrag: false,
-                                        year_created: 1901
-                                    }
-                                );
-                            }
-                            x if x.starts_with("scripts") && x.ends_with("arenas.txt") => {
-                                arena_files.push(name.to_string());
-                            }
-                            x if x.starts_with("scripts") && x.ends_with(".arena") => {
-                                arena_files.push(name.to_string());
-                            }
-                            x if x.starts_with("scripts") && x.ends_with(".defi") => {
-                                defi_files.push(name.to_string());
-                            }
-                            _ => continue
-                        }
-					}
-                    if get_all_data {
-                        Level::get_remaining_data(&mut pk3_maps, arena_files, defi_files, &mut archive);
-                    }                 
-                    maps.append(&mut pk3_maps);          
-				}
-			}
-		}
-		Ok(maps)
+        Ok(pk3_maps)
 	}
 
-    pub fn get_remaining_data(pk3_maps: &mut Vec<Level>, arena_files: Vec<String>, defi_files: Vec<String>, archive: &mut ZipArchive<File>) {
+    pub fn get_remaining_data(pk3_maps: &mut Vec<Level>, arena_files: &[String], defi_files: &[String], reader: &mut VfsReader) {
         
         let mut arena_data: Vec<HashMap<String, String>> = vec![];
         let mut defi_data: Vec<HashMap<String, String>> = vec![];
 
         for arena in arena_files {
-            let arena_file = archive.by_name(&arena);
-            if let Ok(mut file) = arena_file {
-                let mut contents = String::from("");
-                let x = file.read_to_string(&mut contents);
-                if let Ok(_good_read) = x {
-                    arena_data.extend(Self::parse_arena_data(contents.clone()));
-                }
+            if let Ok(contents) = reader.read(arena) {
+                arena_data.extend(Self::parse_arena_data(String::from_utf8_lossy(&contents).to_string()));
             }
         }
 
         for defi in defi_files {
-            let defi_file = archive.by_name(&defi);
-            if let Ok(mut file) = defi_file {
-                let mut contents = String::from("");
-                let x = file.read_to_string(&mut contents);
-                if let Ok(_good_read) = x {
-                    defi_data.extend(Self::parse_arena_data(contents.clone()));
-                }
+            if let Ok(contents) = reader.read(defi) {
+                defi_data.extend(Self::parse_arena_data(String::from_utf8_lossy(&contents).to_string()));
             }
         }
 
@@ -131,12 +118,18 @@ impl Level {
                 m.author = String::from("id Software");
                 m.author_vhtml = String::from("id Software");
             }
-            let bsp = archive.by_name(format!("maps/{}.bsp", m.level_name).as_str());
-            if let Ok(bsp_map) = bsp {
-                let last_date = bsp_map.last_modified();
-                if let Some(date) = last_date {
-                    m.year_created = date.year();
-                }
+            let bsp_name = format!("maps/{}.bsp", m.level_name);
+            if let Some(year) = reader.year(&bsp_name) {
+                m.year_created = year;
+            }
+            let entities = match reader.seek_reader(&bsp_name).map(LevelEntities::from_bsp) {
+                Ok(entities) => Some(entities),
+                Err(_) => reader.reader(&bsp_name).ok().map(LevelEntities::from_bsp_stream),
+            };
+            match entities {
+                Some(Ok(entities)) => m.entities = Some(entities),
+                Some(Err(e)) => log::error!("Could not read entities of {}: {}", m.level_name, e),
+                None => (),
             }
             for d in &arena_data {
                 if *d.get("map").unwrap() == m.level_name.to_lowercase() {
@@ -171,6 +164,22 @@ impl Level {
                     break
                 }
             }
+            if m.gametype.is_empty() {
+                if let Some(entities) = &m.entities {
+                    m.gametype = entities.infer_gametypes();
+                    m.gametype_inferred = !m.gametype.is_empty();
+                }
+            }
+        }
+    }
+
+    // marks which copy of each map the game actually loads, levels come in search order so the first copy of a name wins, see Vfs
+    pub fn resolve_shadowing(levels: &mut [Level]) {
+        let mut loaded: HashMap<String, String> = HashMap::new();
+
+        for level in levels.iter_mut() {
+            let loaded_path = loaded.entry(level.level_name.to_lowercase()).or_insert(level.path.clone());
+            level.shadowed_by = Some(loaded_path.clone()).filter(|path| *path != level.path);
         }
     }
 
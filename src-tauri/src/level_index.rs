@@ -0,0 +1,113 @@
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{read_to_string, rename, File};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::level::Level;
+use crate::vfs::{SourceKind, Vfs};
+
+// a source's levels and the index entry to store for it, None when it came from the index or isn't a pk3
+type SourceScan = (Vec<Level>, Option<(String, IndexedPk3)>);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexedPk3 {
+	pub size: u64,
+	// nanoseconds since the epoch, None where the filesystem doesn't keep one
+	pub modified: Option<u128>,
+	// a scan without the arena, entity and checksum data can't stand in for a full one
+	pub all_data: bool,
+	pub levels: Vec<Level>,
+}
+
+/*
+    the levels found in every pk3 ever scanned, kept in the cache dir so opening the level browser only opens pk3s that changed
+    a pk3 is rescanned when its size or modified time differ from the indexed ones, pk3s that no longer exist are dropped
+    loose maps aren't indexed, they're few and walking the search paths lists them anyway
+    shadowing depends on every source so it's worked out again on each call, never stored
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LevelIndex {
+	// keyed by pk3 path
+	pub pk3s: HashMap<String, IndexedPk3>,
+}
+
+impl LevelIndex {
+	// through a temp file so a crash mid write leaves the old index, not a truncated one
+	pub fn write_to_file(&self, path: &PathBuf) -> Result<(), tauri::Error> {
+		let tmp_path = path.with_extension("json.tmp");
+		let mut file = File::create(&tmp_path)?;
+		let index_string = serde_json::to_string(&self)?;
+		file.write_all(index_string.as_bytes())?;
+		drop(file);
+		rename(&tmp_path, path)?;
+
+		Ok(())
+	}
+
+	// an index that fails to parse was written by an older version, everything gets rescanned
+	pub fn read_from_file(path: &PathBuf) -> Self {
+		read_to_string(path).ok().and_then(|i| serde_json::from_str(&i).ok()).unwrap_or_default()
+	}
+
+	// every level in the search paths in search order, and whether the index changed and should be written back
+	pub fn levels(&mut self, search_paths: &[String], get_all_data: bool) -> Result<(Vec<Level>, bool), Error> {
+		let sources = Vfs::list_sources(search_paths)?;
+
+		let scanned: Vec<SourceScan> = sources
+			.par_iter()
+			.map(|(source, files)| {
+				let path = source.path.to_string_lossy().to_string();
+				let stamp = files
+					.first()
+					.filter(|_| source.kind == SourceKind::Pk3)
+					.map(|(_, modified, size)| (*size, modified.and_then(|m| m.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_nanos())));
+
+				let indexed = stamp.and_then(|(size, modified)| {
+					self.pk3s.get(&path).filter(|i| i.size == size && i.modified == modified && (i.all_data || !get_all_data))
+				});
+
+				if let Some(indexed) = indexed {
+					return (indexed.levels.clone(), None);
+				}
+
+				match Level::get_source_levels(source, files, get_all_data) {
+					Ok(levels) => {
+						let update = stamp.map(|(size, modified)| {
+							(path, IndexedPk3 { size, modified, all_data: get_all_data, levels: levels.clone() })
+						});
+
+						(levels, update)
+					}
+					Err(e) => {
+						log::error!("Could not open pk3, skipping: {}\n{}", path, e);
+						(vec![], None)
+					}
+				}
+			})
+			.collect();
+
+		let mut levels: Vec<Level> = vec![];
+		let mut changed = false;
+
+		for (source_levels, update) in scanned {
+			levels.extend(source_levels);
+
+			if let Some((path, indexed)) = update {
+				self.pk3s.insert(path, indexed);
+				changed = true;
+			}
+		}
+
+		let indexed_count = self.pk3s.len();
+		self.pk3s.retain(|path, _| Path::new(path).is_file());
+		changed |= self.pk3s.len() != indexed_count;
+
+		Level::resolve_shadowing(&mut levels);
+
+		Ok((levels, changed))
+	}
+}
+
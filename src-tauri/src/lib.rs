@@ -1,14 +1,34 @@
 use std::sync::Mutex;
 
+mod bsp;
 mod client;
 mod commands;
 mod config;
 mod demo;
+mod fake_server;
 mod huffman_node;
+mod info_string;
+mod lan;
 mod level;
+mod level_index;
+mod local_master;
+mod map_deps;
 mod master;
+mod overview;
+mod pk3_checksum;
+mod pk3_report;
 mod server;
+mod server_cache;
+mod server_compat;
+mod server_history;
+mod server_query;
+mod server_watch;
+mod shader;
+mod vfs;
+mod watchlist;
 mod q3_util;
+mod rcon;
+mod udp_service;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -22,9 +42,27 @@ pub fn run() {
 			commands::level::get_levels,
 			commands::level::get_cached_levelshots,
 			commands::level::extract_levelshots_to_cache,
+			commands::level::extract_overviews_to_cache,
+			commands::level::check_map_dependencies,
 			commands::master::get_q3_server_ips,
+			commands::master::start_local_master,
+			commands::master::stop_local_master,
 			commands::server::refresh_all_servers,
 			commands::server::refresh_single_server,
+			commands::server::resolve_server_address,
+			commands::server::get_lan_servers,
+			commands::server::start_server_watch,
+			commands::server::stop_server_watch,
+			commands::server::get_cached_servers,
+			commands::server::query_servers,
+			commands::server::get_watched_players,
+			commands::server::get_server_history,
+			commands::server::get_server_stats,
+			commands::server::check_server_compatibility,
+			commands::rcon::send_rcon_command,
+			commands::rcon::set_rcon_password,
+			commands::rcon::get_rcon_history,
+			commands::rcon::has_rcon_password,
 			commands::config::get_config,
 			commands::config::get_appdata,
 			commands::config::save_config,
@@ -34,6 +72,8 @@ pub fn run() {
 			commands::client::kill_q3_client,
             commands::client::get_client_search_paths,
             commands::client::get_client_available_configs,
+            commands::client::get_pk3_overrides,
+            commands::client::get_pk3_checksums,
             commands::client::get_client_q3config,
             commands::client::get_defrag_rec_files,
 			commands::demo::get_demos,
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::info_string::InfoString;
+use crate::master::{GETSERVERS_EXT_RESPONSE, GETSERVERS_RESPONSE, MASTER_EOF, MASTER_EOT};
+use crate::q3_util::OOB;
+use crate::udp_service::UdpServiceHandle;
+
+// same limits dpmaster uses
+const SERVER_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+const MAX_PACKET_SIZE_OUT: usize = 1400;
+
+#[derive(Debug, Clone)]
+pub struct RegisteredServer {
+	pub address: SocketAddr,
+	pub info: InfoString,
+	pub last_heartbeat: Instant,
+	pub validated: bool,
+	challenge: String,
+}
+
+impl RegisteredServer {
+	fn clients(&self) -> u32 {
+		self.info.get("clients").and_then(|c| c.parse::<u32>().ok()).unwrap_or(0)
+	}
+
+	fn maxclients(&self) -> u32 {
+		self.info.get("sv_maxclients").and_then(|c| c.parse::<u32>().ok()).unwrap_or(0)
+	}
+}
+
+/*
+    a minimal dpmaster for LAN events and offline testing
+    heartbeat -> getinfo <challenge> -> infoResponse registers a server, same handshake as dpmaster
+    getservers and getserversExt are answered from the registry, servers expire after SERVER_TIMEOUT
+    clones share the registry, so one kept back from spawn can still look into it
+*/
+#[derive(Clone)]
+pub struct LocalMaster {
+	servers: Arc<Mutex<HashMap<SocketAddr, RegisteredServer>>>,
+	server_timeout: Duration,
+}
+
+impl LocalMaster {
+	pub fn new() -> Self {
+		Self {
+			servers: Arc::new(Mutex::new(HashMap::new())),
+			server_timeout: SERVER_TIMEOUT,
+		}
+	}
+
+	// servers that finished the heartbeat handshake
+	#[cfg(test)]
+	pub fn registered(&self) -> usize {
+		self.servers.lock().unwrap().values().filter(|s| s.validated).count()
+	}
+
+	pub fn spawn(self, socket: UdpSocket) -> Result<UdpServiceHandle, Error> {
+		UdpServiceHandle::spawn(socket, |_| (), move |socket, packet, from| {
+			if let Err(e) = self.handle_packet(socket, packet, from) {
+				log::error!("local master: bad packet from {}: {}", from, e);
+			}
+		})
+	}
+
+	pub fn handle_packet(&self, socket: &UdpSocket, packet: &[u8], from: SocketAddr) -> Result<(), Error> {
+		if !packet.starts_with(OOB) {
+			return Err(Error::new(ErrorKind::InvalidData, "not an out of band packet"));
+		}
+
+		self.expire_servers();
+
+		let message = String::from_utf8_lossy(&packet[OOB.len()..]);
+		let (command, args) = message.split_once(['\n', ' ']).unwrap_or((&message, ""));
+
+		match command {
+			"heartbeat" => self.heartbeat(socket, from),
+			"infoResponse" => self.info_response(args, from),
+			"getservers" => self.getservers(socket, args, false, from),
+			"getserversExt" => self.getservers(socket, args, true, from),
+			_ => Err(Error::new(ErrorKind::InvalidData, format!("unknown command {}", command))),
+		}
+	}
+
+	fn heartbeat(&self, socket: &UdpSocket, from: SocketAddr) -> Result<(), Error> {
+		let challenge = format!(
+			"{:x}{:x}",
+			SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos(),
+			from.port()
+		);
+
+		let mut servers = self.servers.lock().unwrap();
+		let server = servers.entry(from).or_insert(RegisteredServer {
+			address: from,
+			info: InfoString::new(),
+			last_heartbeat: Instant::now(),
+			validated: false,
+			challenge: String::new(),
+		});
+		server.challenge = challenge.clone();
+
+		let mut getinfo = OOB.to_vec();
+		getinfo.extend(format!("getinfo {}", challenge).bytes());
+		socket.send_to(&getinfo, from)?;
+
+		Ok(())
+	}
+
+	fn info_response(&self, args: &str, from: SocketAddr) -> Result<(), Error> {
+		let info = InfoString::parse(args);
+		let mut servers = self.servers.lock().unwrap();
+
+		let server = match servers.get_mut(&from) {
+			Some(s) => s,
+			None => return Err(Error::new(ErrorKind::InvalidData, "infoResponse without a heartbeat")),
+		};
+
+		if info.get("challenge") != Some(server.challenge.as_str()) {
+			return Err(Error::new(ErrorKind::InvalidData, "infoResponse with a wrong challenge"));
+		}
+
+		server.info = info;
+		server.validated = true;
+		server.last_heartbeat = Instant::now();
+
+		Ok(())
+	}
+
+	// getservers <protocol> [keywords] or getserversExt <gamename> <protocol> [keywords]
+	fn getservers(&self, socket: &UdpSocket, args: &str, ext: bool, from: SocketAddr) -> Result<(), Error> {
+		let mut args = args.split_whitespace();
+		let gamename = if ext { args.next().unwrap_or("") } else { "Quake3Arena" };
+		let protocol = args.next().unwrap_or("");
+		let keywords: Vec<&str> = args.collect();
+
+		let want_ipv4 = !ext || !keywords.contains(&"ipv6") || keywords.contains(&"ipv4");
+		let want_ipv6 = ext && (!keywords.contains(&"ipv4") || keywords.contains(&"ipv6"));
+		let gametype = keywords.iter().find_map(|k| k.strip_prefix("gametype="));
+
+		let mut entries: Vec<Vec<u8>> = vec![];
+
+		for server in self.servers.lock().unwrap().values() {
+			if !server.validated || server.info.get("protocol") != Some(protocol) {
+				continue;
+			}
+
+			if !server.info.get("gamename").unwrap_or("Quake3Arena").eq_ignore_ascii_case(gamename) {
+				continue;
+			}
+
+			if (server.clients() == 0 && !keywords.contains(&"empty"))
+				|| (server.clients() >= server.maxclients() && !keywords.contains(&"full"))
+			{
+				continue;
+			}
+
+			if gametype.is_some() && server.info.get("gametype") != gametype {
+				continue;
+			}
+
+			match server.address.ip() {
+				IpAddr::V4(ip) if want_ipv4 => {
+					let mut entry = vec![b'\\'];
+					entry.extend(ip.octets());
+					entry.extend(server.address.port().to_be_bytes());
+					entries.push(entry);
+				}
+				IpAddr::V6(ip) if want_ipv6 => {
+					let mut entry = vec![b'/'];
+					entry.extend(ip.octets());
+					entry.extend(server.address.port().to_be_bytes());
+					entries.push(entry);
+				}
+				_ => continue,
+			}
+		}
+
+		let header = if ext { GETSERVERS_EXT_RESPONSE } else { GETSERVERS_RESPONSE };
+
+		for packet in Self::response_packets(header, entries) {
+			socket.send_to(&packet, from)?;
+		}
+
+		Ok(())
+	}
+
+	// every packet but the last ends in EOF so clients know to keep reading until EOT
+	fn response_packets(header: &[u8], entries: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+		let mut packets: Vec<Vec<u8>> = vec![];
+		let mut packet = header.to_vec();
+
+		for entry in entries {
+			if packet.len() + entry.len() + MASTER_EOT.len() > MAX_PACKET_SIZE_OUT {
+				packet.extend(MASTER_EOF);
+				packets.push(packet);
+				packet = header.to_vec();
+			}
+			packet.extend(entry);
+		}
+
+		packet.extend(MASTER_EOT);
+		packets.push(packet);
+
+		packets
+	}
+
+	fn expire_servers(&self) {
+		self.servers.lock().unwrap().retain(|_, s| s.last_heartbeat.elapsed() < self.server_timeout);
+	}
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind};
+
+use crate::bsp::{BspFile, SURF_NODRAW};
+use crate::vfs::{Vfs, VfsFile};
+
+// R_FindImageFile ignores the extension it's asked for and tries every format the renderer can load, in this order
+const IMAGE_EXTENSIONS: [&str; 6] = ["tga", "jpg", "jpeg", "png", "pcx", "bmp"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+	Shader,
+	Texture,
+	Model,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Dependency {
+	pub name: String,
+	pub kind: DependencyKind,
+	// the pk3 or loose file it's loaded from, None when it's missing
+	pub provided_by: Option<String>,
+	// the shader that loads a texture, empty for what the bsp references itself
+	pub required_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MapDependencies {
+	pub level_name: String,
+	pub bsp: String,
+	pub dependencies: Vec<Dependency>,
+	pub missing: usize,
+}
+
+/*
+    checks what the loaded copy of a map needs against the client's vfs
+    a bsp shader is either defined in a shader script, whose stage images are checked too, or is a plain texture of the same name
+    surfaces that never draw (caulk, clip, hint...) are skipped since a missing one can't be seen
+    misc_model geometry is compiled into the bsp, only models loaded at runtime (model2, brushless model keys) are checked
+*/
+pub fn check_dependencies(vfs: &Vfs, level_name: &str) -> Result<MapDependencies, Error> {
+	let bsp_name = format!("maps/{}.bsp", level_name);
+	let bsp_file = vfs
+		.lookup(&bsp_name)
+		.ok_or(Error::new(ErrorKind::NotFound, format!("{} is not in any search path", bsp_name)))?;
+
+	let bsp = BspFile::read(vfs.open(bsp_file.source)?.reader(&bsp_file.name)?)?;
+
+	let mut report = MapDependencies {
+		level_name: level_name.to_string(),
+		bsp: vfs.disk_path(bsp_file).to_string_lossy().to_string(),
+		dependencies: vec![],
+		missing: 0,
+	};
+
+	let mut seen: HashSet<(DependencyKind, String)> = HashSet::new();
+
+	let shaders = bsp
+		.shaders()?
+		.into_iter()
+		.filter(|(name, flags)| flags & SURF_NODRAW == 0 && !name.eq_ignore_ascii_case("noshader"))
+		.map(|(name, _)| name)
+		.chain(bsp.fog_shaders()?);
+
+	for shader in shaders {
+		if !seen.insert((DependencyKind::Shader, shader.to_lowercase())) {
+			continue;
+		}
+
+		if let Some((script, def)) = vfs.shaders().get(&shader) {
+			report.add(vfs, &shader, DependencyKind::Shader, Some(script), "");
+
+			for image in &def.images {
+				if seen.insert((DependencyKind::Texture, image.to_lowercase())) {
+					report.add(vfs, image, DependencyKind::Texture, find_image(vfs, image), &shader);
+				}
+			}
+
+			continue;
+		}
+
+		match find_image(vfs, &shader) {
+			Some(image) => report.add(vfs, &shader, DependencyKind::Texture, Some(image), ""),
+			None => report.add(vfs, &shader, DependencyKind::Shader, None, ""),
+		}
+	}
+
+	for entity in bsp.entities()? {
+		let misc_model = entity.get("classname").is_some_and(|c| c.eq_ignore_ascii_case("misc_model"));
+		let models = [entity.get("model").filter(|_| !misc_model), entity.get("model2")];
+
+		// *1, *2... are the bsp's own brush models
+		for model in models.into_iter().flatten().filter(|m| !m.starts_with('*')) {
+			if seen.insert((DependencyKind::Model, model.to_lowercase())) {
+				report.add(vfs, model, DependencyKind::Model, vfs.lookup(model), "");
+			}
+		}
+	}
+
+	Ok(report)
+}
+
+impl MapDependencies {
+	fn add(&mut self, vfs: &Vfs, name: &str, kind: DependencyKind, file: Option<&VfsFile>, required_by: &str) {
+		if file.is_none() {
+			self.missing += 1;
+		}
+
+		self.dependencies.push(Dependency {
+			name: name.to_string(),
+			kind,
+			provided_by: file.map(|f| vfs.disk_path(f).to_string_lossy().to_string()),
+			required_by: required_by.to_string(),
+		});
+	}
+}
+
+fn find_image<'a>(vfs: &'a Vfs, name: &str) -> Option<&'a VfsFile> {
+	let stem = match name.rsplit_once('.') {
+		Some((stem, ext)) if !ext.contains('/') => stem,
+		_ => name,
+	};
+
+	IMAGE_EXTENSIONS.iter().find_map(|ext| vfs.lookup(&format!("{}.{}", stem, ext)))
+}
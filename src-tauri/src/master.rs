@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::str;
+use std::time::{Duration, Instant};
 
 use crate::server::Quake3Server;
+use crate::q3_util::OOB;
 
 #[derive(Debug, Default, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Clone)]
 pub struct MasterServer {
@@ -10,49 +14,226 @@ pub struct MasterServer {
 	pub game: String,
 	pub active: bool,
 	pub unreachable: bool,
+	#[serde(default)]
+	pub gamename: String,
+	#[serde(default = "MasterServer::default_filters")]
+	pub filters: String,
+	#[serde(default)]
+	pub protocol: Option<u8>,
+}
+
+pub const GETSERVERS_RESPONSE: &[u8] = b"\xff\xff\xff\xffgetserversResponse";
+pub const GETSERVERS_EXT_RESPONSE: &[u8] = b"\xff\xff\xff\xffgetserversExtResponse";
+pub const MASTER_EOT: &[u8] = b"\\EOT\0\0\0";
+pub const MASTER_EOF: &[u8] = b"\\EOF\0\0\0";
+
+const MASTER_RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+const MASTER_STRAGGLER_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Default)]
+pub struct MasterResponse {
+	pub servers: Vec<SocketAddr>,
+	pub packets: usize,
+	pub duplicates: usize,
+	pub complete: bool,
+	pub latency: Option<Duration>,
+	seen: HashSet<SocketAddr>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MasterStatus {
+	pub master: MasterServer,
+	pub reachable: bool,
+	pub latency: u16,
+	pub servers: usize,
+	// addresses the master sent more than once, only the first is kept
+	#[serde(default)]
+	pub duplicates: usize,
+	pub complete: bool,
+	pub error: Option<String>,
+}
+
+impl MasterStatus {
+	pub fn new(master: &MasterServer) -> Self {
+		Self {
+			master: master.to_owned(),
+			reachable: false,
+			latency: 0,
+			servers: 0,
+			duplicates: 0,
+			complete: false,
+			error: None,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MasterQueryResult {
+	pub servers: Vec<Quake3Server>,
+	pub masters: Vec<MasterStatus>,
+}
+
+impl MasterResponse {
+	fn add_packet(&mut self, packet: &[u8]) -> bool {
+		let (addresses, eot) = match MasterServer::parse_master_response(packet) {
+			Some(parsed) => parsed,
+			None => return false,
+		};
+
+		self.packets += 1;
+		self.complete = self.complete || eot;
+
+		for address in addresses {
+			if self.seen.insert(address) {
+				self.servers.push(address);
+			} else {
+				self.duplicates += 1;
+			}
+		}
+
+		true
+	}
 }
 
 impl MasterServer {
-	pub fn parse_master_response(
-		response: &[u8],
-		master: &MasterServer,
-		servers_so_far: &Vec<Quake3Server>,
-		protocol: &u8,
-	) -> Vec<Quake3Server> {
-		let mut parsed_master_servers: Vec<Quake3Server> = vec![];
-
-		let mut index: usize = 0;
-
-		if &response[index..22] == b"\xff\xff\xff\xffgetserversResponse" {
-			index += 23;
+	pub fn default_filters() -> String {
+		String::from("full empty")
+	}
+
+	// masters without a protocol follow the Quake 3 protocol picked in the server browser
+	pub fn query_protocol(&self, q3_protocol: u8) -> u8 {
+		self.protocol.unwrap_or(q3_protocol)
+	}
+
+	// masters saved before the query was configurable only had a game label to go on
+	pub fn migrate(&mut self) {
+		match self.game.as_str() {
+			"Urban Terror" => _ = self.protocol.get_or_insert(68),
+			"OpenArena" => _ = self.protocol.get_or_insert(71),
+			_ => (),
+		}
+
+		let host = self.address.split(':').next().unwrap_or("");
+
+		if self.gamename.is_empty() && (host == "dpmaster.deathmask.net" || host == "master.ioquake3.org") {
+			self.gamename = String::from("Quake3Arena");
+		}
+	}
+
+	/*
+		masters without a gamename get a plain getservers <protocol> [filters]
+		with one, dpmaster's getserversExt <gamename> <protocol> [filters] only returns that game
+		IPv6 lists are only available through getserversExt
+	*/
+	pub fn getservers_query(&self, protocol: u8, ipv6: bool) -> Vec<u8> {
+		let mut query = OOB.to_vec();
+		let filters: Vec<&str> = self.filters.split_whitespace().filter(|f| *f != "ipv4" && *f != "ipv6").collect();
+
+		if self.gamename.is_empty() && !ipv6 {
+			query.extend(format!("getservers {} {}", protocol, filters.join(" ")).trim_end().bytes());
+			return query;
+		}
+
+		query.extend(
+			format!("getserversExt {} {} {} {}", self.ext_gamename(), protocol, if ipv6 { "ipv6" } else { "ipv4" }, filters.join(" "))
+				.trim_end()
+				.bytes(),
+		);
+		query
+	}
+
+	// the gamename servers send in their heartbeats, masters without one fall back on their game label
+	fn ext_gamename(&self) -> &str {
+		if !self.gamename.is_empty() {
+			return &self.gamename;
+		}
+
+		match self.game.as_str() {
+			"Urban Terror" => "Quake3-UrT",
+			_ => "Quake3Arena",
+		}
+	}
+
+	/*
+		entries are \ + 4 byte IPv4 + 2 byte port, or / + 16 byte IPv6 + 2 byte port
+		IPv6 entries only come back from getserversExt, see dpmaster's technical docs
+		the last packet of a list ends with \EOT\0\0\0, dpmaster ends the others with \EOF\0\0\0
+	*/
+	pub fn parse_master_response(response: &[u8]) -> Option<(Vec<SocketAddr>, bool)> {
+		let mut addresses: Vec<SocketAddr> = vec![];
+		let mut eot = false;
+
+		let mut index: usize;
+
+		if response.starts_with(GETSERVERS_EXT_RESPONSE) {
+			index = GETSERVERS_EXT_RESPONSE.len();
+		} else if response.starts_with(GETSERVERS_RESPONSE) {
+			index = GETSERVERS_RESPONSE.len();
 		} else {
-			return parsed_master_servers;
+			return None;
 		}
 
-		while (index + 5) < response.len() {
-			let ip = format!(
-				"{0}.{1}.{2}.{3}",
-				&response[index],
-				&response[index + 1],
-				&response[index + 2],
-				&response[index + 3]
-			);
-			let port = u16::from_be_bytes([response[index + 4], response[index + 5]]).to_string();
-
-			if port == "0" {
+		while index < response.len() {
+			let address: SocketAddr;
+			let rest = &response[index..];
+
+			if rest.starts_with(MASTER_EOT) || rest == b"\\EOT" {
+				eot = true;
 				break;
 			}
 
-			let parsed = Quake3Server::new(ip, port, Some(master.to_owned()), Some(protocol.to_owned()));
+			if rest.starts_with(MASTER_EOF) {
+				break;
+			}
+
+			match response[index] {
+				b'\\' if index + 7 <= response.len() => {
+					let ip: [u8; 4] = response[index + 1..index + 5].try_into().unwrap();
+					let port = u16::from_be_bytes([response[index + 5], response[index + 6]]);
+					address = SocketAddr::new(IpAddr::from(ip), port);
+					index += 7;
+				}
+				b'/' if index + 19 <= response.len() => {
+					let ip: [u8; 16] = response[index + 1..index + 17].try_into().unwrap();
+					let port = u16::from_be_bytes([response[index + 17], response[index + 18]]);
+					address = SocketAddr::new(IpAddr::from(ip), port);
+					index += 19;
+				}
+				_ => break,
+			}
 
-			if !servers_so_far.iter().any(|serv| serv.address == parsed.address) {
-				parsed_master_servers.push(parsed);
+			if address.port() != 0 && !address.ip().is_unspecified() {
+				addresses.push(address);
 			}
+		}
+
+		Some((addresses, eot))
+	}
+
+	/*
+		collects packets until the master sends EOT or stops responding, a list can span many packets
+		UDP doesn't keep them in order so anything still in flight after EOT is picked up as well
+	*/
+	pub fn read_master_response(socket: &UdpSocket) -> MasterResponse {
+		let mut response = MasterResponse::default();
+		let mut buf: Vec<u8> = vec![0; 65507];
+		let started = Instant::now();
 
-			index += 7;
+		while started.elapsed() < MASTER_RESPONSE_TIMEOUT {
+			match socket.recv(&mut buf) {
+				Ok(bytes) => {
+					if response.latency.is_none() {
+						response.latency = Some(started.elapsed());
+					}
+					if response.add_packet(&buf[..bytes]) && response.complete {
+						let _ = socket.set_read_timeout(Some(MASTER_STRAGGLER_TIMEOUT));
+					}
+				}
+				Err(_err) => break,
+			}
 		}
 
-		parsed_master_servers
+		response
 	}
 
 	pub fn initial_masters() -> Vec<MasterServer> {
@@ -63,6 +244,9 @@ impl MasterServer {
 				game: String::from("Quake 3"),
 				active: true,
 				unreachable: false,
+				gamename: String::from(""),
+				filters: MasterServer::default_filters(),
+				protocol: None,
 			},
 			MasterServer {
 				name: String::from("master.ioquake3.org"),
@@ -70,6 +254,9 @@ impl MasterServer {
 				game: String::from("Quake 3"),
 				active: true,
 				unreachable: false,
+				gamename: String::from("Quake3Arena"),
+				filters: MasterServer::default_filters(),
+				protocol: None,
 			},
 			MasterServer {
 				name: String::from("dpmaster.deathmask.net"),
@@ -77,6 +264,9 @@ impl MasterServer {
 				game: String::from("Quake 3"),
 				active: true,
 				unreachable: false,
+				gamename: String::from("Quake3Arena"),
+				filters: MasterServer::default_filters(),
+				protocol: None,
 			},
 			MasterServer {
 				name: String::from("master.urbanterror.info"),
@@ -84,6 +274,9 @@ impl MasterServer {
 				game: String::from("Urban Terror"),
 				active: true,
 				unreachable: false,
+				gamename: String::from(""),
+				filters: MasterServer::default_filters(),
+				protocol: Some(68),
 			},
 			MasterServer {
 				name: String::from("master.ioquake3.org"),
@@ -91,6 +284,9 @@ impl MasterServer {
 				game: String::from("OpenArena"),
 				active: true,
 				unreachable: false,
+				gamename: String::from("Quake3Arena"),
+				filters: MasterServer::default_filters(),
+				protocol: Some(71),
 			},
 			MasterServer {
 				name: String::from("dpmaster.deathmask.net"),
@@ -98,9 +294,159 @@ impl MasterServer {
 				game: String::from("OpenArena"),
 				active: true,
 				unreachable: false,
+				gamename: String::from("Quake3Arena"),
+				filters: MasterServer::default_filters(),
+				protocol: Some(71),
 			}
 		];
 
 		initial
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+	use std::time::Duration;
+
+	use super::{MasterResponse, MasterServer, GETSERVERS_EXT_RESPONSE, GETSERVERS_RESPONSE, MASTER_EOF, MASTER_EOT};
+
+	fn ipv4_entry(address: SocketAddr) -> Vec<u8> {
+		let mut entry = vec![b'\\'];
+		if let IpAddr::V4(ip) = address.ip() {
+			entry.extend(ip.octets());
+		}
+		entry.extend(address.port().to_be_bytes());
+		entry
+	}
+
+	fn ipv6_entry(address: SocketAddr) -> Vec<u8> {
+		let mut entry = vec![b'/'];
+		if let IpAddr::V6(ip) = address.ip() {
+			entry.extend(ip.octets());
+		}
+		entry.extend(address.port().to_be_bytes());
+		entry
+	}
+
+	fn ipv4(a: u8, b: u8, c: u8, d: u8, port: u16) -> SocketAddr {
+		SocketAddr::new(IpAddr::V4(Ipv4Addr::new(a, b, c, d)), port)
+	}
+
+	fn ipv6(last: u16, port: u16) -> SocketAddr {
+		SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last)), port)
+	}
+
+	// a getserversResponse packet with the given servers and end marker
+	fn list_packet(servers: &[SocketAddr], end: &[u8]) -> Vec<u8> {
+		let mut packet = GETSERVERS_RESPONSE.to_vec();
+		for server in servers {
+			packet.extend(ipv4_entry(*server));
+		}
+		packet.extend(end);
+		packet
+	}
+
+	#[test]
+	fn ipv4_and_ipv6_entries_are_parsed_together() {
+		let servers = vec![ipv4(192, 0, 2, 1, 27960), ipv6(1, 27961), ipv4(198, 51, 100, 7, 27962), ipv6(2, 27963)];
+
+		let mut packet = GETSERVERS_EXT_RESPONSE.to_vec();
+		packet.extend(ipv4_entry(servers[0]));
+		packet.extend(ipv6_entry(servers[1]));
+		packet.extend(ipv4_entry(servers[2]));
+		packet.extend(ipv6_entry(servers[3]));
+		packet.extend(MASTER_EOT);
+
+		assert_eq!(MasterServer::parse_master_response(&packet), Some((servers, true)));
+	}
+
+	// a / with fewer than 18 bytes after it can't be read as an address, the entries before it still count
+	#[test]
+	fn truncated_ipv6_entry_is_dropped() {
+		let server = ipv4(192, 0, 2, 1, 27960);
+
+		let mut packet = GETSERVERS_EXT_RESPONSE.to_vec();
+		packet.extend(ipv4_entry(server));
+		packet.extend(&ipv6_entry(ipv6(1, 27961))[..12]);
+
+		assert_eq!(MasterServer::parse_master_response(&packet), Some((vec![server], false)));
+	}
+
+	#[test]
+	fn list_spans_packets_until_eot() {
+		let first = [ipv4(192, 0, 2, 1, 27960), ipv4(192, 0, 2, 2, 27960)];
+		let last = [ipv4(192, 0, 2, 3, 27960)];
+		let mut response = MasterResponse::default();
+
+		assert!(response.add_packet(&list_packet(&first, MASTER_EOF)));
+		assert!(!response.complete);
+
+		assert!(response.add_packet(&list_packet(&last, MASTER_EOT)));
+		assert!(response.complete);
+
+		assert_eq!(response.servers, [first.as_slice(), last.as_slice()].concat());
+		assert_eq!(response.packets, 2);
+		assert_eq!(response.duplicates, 0);
+	}
+
+	// UDP can hand the EOT packet over first or twice, the list has to come out the same
+	#[test]
+	fn duplicate_and_out_of_order_packets() {
+		let first = [ipv4(192, 0, 2, 1, 27960), ipv4(192, 0, 2, 2, 27960)];
+		let last = [ipv4(192, 0, 2, 2, 27960), ipv4(192, 0, 2, 3, 27960)];
+		let mut response = MasterResponse::default();
+
+		response.add_packet(&list_packet(&last, MASTER_EOT));
+		response.add_packet(&list_packet(&first, MASTER_EOF));
+		response.add_packet(&list_packet(&last, MASTER_EOT));
+
+		assert!(response.complete);
+		assert_eq!(response.servers, vec![last[0], last[1], first[0]]);
+		assert_eq!(response.packets, 3);
+		assert_eq!(response.duplicates, 3);
+	}
+
+	// used to slice a fixed range past the end of the packet and panic
+	#[test]
+	fn short_trailing_entry_is_ignored() {
+		let server = ipv4(192, 0, 2, 1, 27960);
+
+		let mut packet = list_packet(&[server], b"");
+		packet.extend(&ipv4_entry(ipv4(192, 0, 2, 2, 27960))[..4]);
+
+		assert_eq!(MasterServer::parse_master_response(&packet), Some((vec![server], false)));
+
+		// some masters drop the zeros after the last EOT
+		let packet = list_packet(&[server], b"\\EOT");
+		assert_eq!(MasterServer::parse_master_response(&packet), Some((vec![server], true)));
+	}
+
+	#[test]
+	fn other_packets_are_not_counted() {
+		let mut response = MasterResponse::default();
+
+		assert!(!response.add_packet(b"\xff\xff\xff\xffstatusResponse\n"));
+		assert_eq!(response.packets, 0);
+	}
+
+	// the master stopped answering before EOT, whatever arrived is kept but the list is marked partial
+	#[test]
+	fn list_without_eot_is_partial() {
+		let master = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+		client.connect(master.local_addr().unwrap()).unwrap();
+
+		let servers = [ipv4(192, 0, 2, 1, 27960), ipv4(192, 0, 2, 2, 27960)];
+		master.send_to(&list_packet(&servers[..1], MASTER_EOF), client.local_addr().unwrap()).unwrap();
+		master.send_to(&list_packet(&servers[1..], MASTER_EOF), client.local_addr().unwrap()).unwrap();
+
+		let response = MasterServer::read_master_response(&client);
+
+		assert!(!response.complete);
+		assert_eq!(response.servers, servers.to_vec());
+		assert_eq!(response.packets, 2);
+		assert!(response.latency.is_some());
+	}
+}
@@ -0,0 +1,293 @@
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+use crate::bsp::{BspFile, LUMP_DRAWINDEXES, LUMP_DRAWVERTS, LUMP_MODELS, LUMP_SHADERS, LUMP_SURFACES, SURF_NODRAW, SURF_SKY};
+
+// longest side of the rendered image, the map is scaled to fit inside the padding
+const OVERVIEW_SIZE: f32 = 512.0;
+const PADDING: f32 = 16.0;
+
+// surface flags and contents from qfiles.h / surfaceflags.h
+const CONTENTS_LAVA: i32 = 8;
+const CONTENTS_SLIME: i32 = 16;
+const CONTENTS_WATER: i32 = 32;
+const CONTENTS_FOG: i32 = 64;
+
+const MST_PLANAR: i32 = 1;
+const MST_PATCH: i32 = 2;
+const MST_TRIANGLE_SOUP: i32 = 3;
+
+// anything steeper is a wall and left out, walls show up as edges between floors instead
+const MIN_FLOOR_NORMAL_Z: f32 = 0.7;
+// a height difference bigger than a step gets outlined
+const EDGE_HEIGHT: f32 = 24.0;
+
+const BACKGROUND: Rgb<u8> = Rgb([18, 22, 28]);
+const FLOOR_LOW: [f32; 3] = [40.0, 58.0, 86.0];
+const FLOOR_HIGH: [f32; 3] = [205.0, 212.0, 220.0];
+const WATER: [f32; 3] = [40.0, 90.0, 200.0];
+const LAVA: [f32; 3] = [210.0, 80.0, 20.0];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Material {
+	Empty,
+	Floor,
+	Water,
+	Lava,
+}
+
+struct Triangle {
+	points: [[f32; 3]; 3],
+	material: Material,
+}
+
+/*
+    a top down view of every floor in the world model, shaded by height
+    floors are upward facing triangles from the draw surfaces, patches use their control grid
+    where floors overlap the highest one wins, so upper levels cover what's beneath them
+*/
+pub fn render_overview(bsp: &BspFile) -> Result<RgbImage, Error> {
+	let triangles = floor_triangles(bsp)?;
+
+	if triangles.is_empty() {
+		return Err(Error::new(ErrorKind::InvalidData, "no floors to draw"));
+	}
+
+	let mut mins = [f32::MAX; 3];
+	let mut maxs = [f32::MIN; 3];
+
+	for p in triangles.iter().flat_map(|t| t.points.iter()) {
+		for axis in 0..3 {
+			mins[axis] = mins[axis].min(p[axis]);
+			maxs[axis] = maxs[axis].max(p[axis]);
+		}
+	}
+
+	let scale = (OVERVIEW_SIZE - 2.0 * PADDING) / (maxs[0] - mins[0]).max(maxs[1] - mins[1]).max(1.0);
+	let width = ((maxs[0] - mins[0]) * scale + 2.0 * PADDING).ceil() as u32;
+	let height = ((maxs[1] - mins[1]) * scale + 2.0 * PADDING).ceil() as u32;
+
+	// world y points up the screen
+	let to_pixel = |p: &[f32; 3]| -> (f32, f32) { ((p[0] - mins[0]) * scale + PADDING, (maxs[1] - p[1]) * scale + PADDING) };
+
+	let mut depth: Vec<f32> = vec![f32::MIN; (width * height) as usize];
+	let mut materials: Vec<Material> = vec![Material::Empty; (width * height) as usize];
+
+	for t in &triangles {
+		let screen = t.points.map(|p| to_pixel(&p));
+		rasterize(&screen, &t.points.map(|p| p[2]), width, height, |i, z| {
+			if z > depth[i] {
+				depth[i] = z;
+				materials[i] = t.material;
+			}
+		});
+	}
+
+	let mut image = RgbImage::from_pixel(width, height, BACKGROUND);
+	let z_range = (maxs[2] - mins[2]).max(1.0);
+
+	for y in 0..height {
+		for x in 0..width {
+			let i = (y * width + x) as usize;
+
+			let t = (depth[i] - mins[2]) / z_range;
+
+			// liquids keep most of their colour so they stand out from the floors
+			let mut color = match materials[i] {
+				Material::Empty => continue,
+				Material::Floor => lerp(FLOOR_LOW, FLOOR_HIGH, t),
+				Material::Water => lerp(WATER, FLOOR_HIGH, t * 0.3),
+				Material::Lava => lerp(LAVA, FLOOR_HIGH, t * 0.3),
+			};
+
+			if is_edge(&depth, &materials, x, y, width, height) {
+				color = color.map(|c| c * 0.55);
+			}
+
+			image.put_pixel(x, y, Rgb(color.map(|c| c.clamp(0.0, 255.0) as u8)));
+		}
+	}
+
+	if let Ok(entities) = bsp.entities() {
+		draw_markers(&mut image, &entities, &to_pixel);
+	}
+
+	Ok(image)
+}
+
+fn floor_triangles(bsp: &BspFile) -> Result<Vec<Triangle>, Error> {
+	let shaders: Vec<(i32, i32)> = bsp.lump(LUMP_SHADERS)?.chunks_exact(72).map(|s| (read_i32(s, 64), read_i32(s, 68))).collect();
+
+	let verts: Vec<([f32; 3], [f32; 3])> = bsp
+		.lump(LUMP_DRAWVERTS)?
+		.chunks_exact(44)
+		.map(|v| ([read_f32(v, 0), read_f32(v, 4), read_f32(v, 8)], [read_f32(v, 28), read_f32(v, 32), read_f32(v, 36)]))
+		.collect();
+
+	let indexes: Vec<i32> = bsp.lump(LUMP_DRAWINDEXES)?.chunks_exact(4).map(|i| read_i32(i, 0)).collect();
+	let surfaces: Vec<&[u8]> = bsp.lump(LUMP_SURFACES)?.chunks_exact(104).collect();
+
+	// model 0 is the world, the others are doors, platforms and other movers
+	let world = bsp.lump(LUMP_MODELS)?;
+	let (first_surface, num_surfaces) = if world.len() >= 40 {
+		(read_i32(world, 24) as usize, read_i32(world, 28) as usize)
+	} else {
+		(0, surfaces.len())
+	};
+
+	let mut triangles: Vec<Triangle> = vec![];
+
+	for surface in surfaces.iter().skip(first_surface).take(num_surfaces) {
+		let (flags, contents) = shaders.get(read_i32(surface, 0) as usize).copied().unwrap_or((0, 0));
+
+		if flags & (SURF_SKY | SURF_NODRAW) != 0 || contents & CONTENTS_FOG != 0 {
+			continue;
+		}
+
+		let material = match contents {
+			c if c & CONTENTS_LAVA != 0 => Material::Lava,
+			c if c & (CONTENTS_WATER | CONTENTS_SLIME) != 0 => Material::Water,
+			_ => Material::Floor,
+		};
+
+		let first_vert = read_i32(surface, 12) as usize;
+		let num_verts = read_i32(surface, 16) as usize;
+		let first_index = read_i32(surface, 20) as usize;
+		let num_indexes = read_i32(surface, 24) as usize;
+
+		let mut corners: Vec<[usize; 3]> = vec![];
+
+		match read_i32(surface, 8) {
+			MST_PLANAR | MST_TRIANGLE_SOUP => {
+				for tri in indexes.iter().skip(first_index).take(num_indexes).collect::<Vec<_>>().chunks_exact(3) {
+					corners.push([0, 1, 2].map(|c| first_vert + *tri[c] as usize));
+				}
+			}
+			MST_PATCH => {
+				let patch_width = read_i32(surface, 96).max(0) as usize;
+				let patch_height = read_i32(surface, 100).max(0) as usize;
+
+				for row in 0..patch_height.saturating_sub(1) {
+					for col in 0..patch_width.saturating_sub(1) {
+						let v = first_vert + row * patch_width + col;
+						corners.push([v, v + 1, v + patch_width]);
+						corners.push([v + 1, v + patch_width + 1, v + patch_width]);
+					}
+				}
+			}
+			_ => continue,
+		}
+
+		for c in corners {
+			if c.iter().any(|v| *v >= verts.len() || *v >= first_vert + num_verts) {
+				continue;
+			}
+
+			let normal_z = c.iter().map(|v| verts[*v].1[2]).sum::<f32>() / 3.0;
+
+			if normal_z < MIN_FLOOR_NORMAL_Z && material == Material::Floor {
+				continue;
+			}
+
+			triangles.push(Triangle {
+				points: c.map(|v| verts[v].0),
+				material,
+			});
+		}
+	}
+
+	Ok(triangles)
+}
+
+// fills every pixel whose centre falls inside the triangle, z is interpolated across it
+fn rasterize(screen: &[(f32, f32); 3], z: &[f32; 3], width: u32, height: u32, mut plot: impl FnMut(usize, f32)) {
+	let [(x0, y0), (x1, y1), (x2, y2)] = *screen;
+	let area = (x1 - x0) * (y2 - y0) - (x2 - x0) * (y1 - y0);
+
+	if area.abs() < f32::EPSILON {
+		return;
+	}
+
+	let min_x = x0.min(x1).min(x2).floor().max(0.0) as u32;
+	let max_x = (x0.max(x1).max(x2).ceil() as u32).min(width - 1);
+	let min_y = y0.min(y1).min(y2).floor().max(0.0) as u32;
+	let max_y = (y0.max(y1).max(y2).ceil() as u32).min(height - 1);
+
+	for py in min_y..=max_y {
+		for px in min_x..=max_x {
+			let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+
+			let w0 = ((x1 - cx) * (y2 - cy) - (x2 - cx) * (y1 - cy)) / area;
+			let w1 = ((x2 - cx) * (y0 - cy) - (x0 - cx) * (y2 - cy)) / area;
+			let w2 = 1.0 - w0 - w1;
+
+			if w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0 {
+				plot((py * width + px) as usize, w0 * z[0] + w1 * z[1] + w2 * z[2]);
+			}
+		}
+	}
+}
+
+fn is_edge(depth: &[f32], materials: &[Material], x: u32, y: u32, width: u32, height: u32) -> bool {
+	let i = (y * width + x) as usize;
+	let neighbours = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+
+	neighbours.iter().any(|(nx, ny)| {
+		if *nx >= width || *ny >= height {
+			return true;
+		}
+		let n = (ny * width + nx) as usize;
+		materials[n] == Material::Empty || (depth[n] - depth[i]).abs() > EDGE_HEIGHT
+	})
+}
+
+fn draw_markers(image: &mut RgbImage, entities: &[HashMap<String, String>], to_pixel: &impl Fn(&[f32; 3]) -> (f32, f32)) {
+	for entity in entities {
+		let classname = entity.get("classname").map(|c| c.to_lowercase()).unwrap_or_default();
+
+		let (color, radius) = match classname.as_str() {
+			"info_player_deathmatch" | "info_player_start" => (Rgb([60, 220, 90]), 3),
+			"team_ctf_redspawn" | "team_ctf_redplayer" => (Rgb([240, 90, 90]), 3),
+			"team_ctf_bluespawn" | "team_ctf_blueplayer" => (Rgb([90, 140, 255]), 3),
+			"team_ctf_redflag" => (Rgb([255, 30, 30]), 6),
+			"team_ctf_blueflag" => (Rgb([40, 90, 255]), 6),
+			"item_quad" | "item_enviro" | "item_haste" | "item_invis" | "item_regen" | "item_flight" => (Rgb([230, 60, 230]), 5),
+			c if c.starts_with("weapon_") => (Rgb([255, 150, 30]), 4),
+			c if c.starts_with("item_armor_") => (Rgb([250, 230, 40]), 4),
+			c if c.starts_with("item_health") => (Rgb([60, 230, 230]), 3),
+			_ => continue,
+		};
+
+		let origin: Vec<f32> = entity.get("origin").map(|o| o.split_whitespace().filter_map(|v| v.parse().ok()).collect()).unwrap_or_default();
+
+		if origin.len() != 3 {
+			continue;
+		}
+
+		let (cx, cy) = to_pixel(&[origin[0], origin[1], origin[2]]);
+		let r = radius as f32;
+
+		for y in (cy - r).floor() as i64..=(cy + r).ceil() as i64 {
+			for x in (cx - r).floor() as i64..=(cx + r).ceil() as i64 {
+				let inside = (x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2) <= r * r;
+
+				if inside && x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+					image.put_pixel(x as u32, y as u32, color);
+				}
+			}
+		}
+	}
+}
+
+fn lerp(from: [f32; 3], to: [f32; 3], t: f32) -> [f32; 3] {
+	[0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t.clamp(0.0, 1.0))
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> i32 {
+	i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_f32(bytes: &[u8], at: usize) -> f32 {
+	f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
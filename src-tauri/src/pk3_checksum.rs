@@ -0,0 +1,204 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::vfs::{SourceKind, Vfs};
+
+/*
+    pk3 checksums the way FS_LoadZipFile in qcommon/files.c builds them
+    the crc of every non empty file in central directory order, as little endian longs, through Com_BlockChecksum
+    checksum is what servers list in sv_paks and sv_referencedPaks, pure checksum puts the server's checksum feed first
+    both are printed signed by the game
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pk3Checksum {
+	pub checksum: i32,
+	#[serde(skip)]
+	crcs: Vec<u32>,
+}
+
+impl Pk3Checksum {
+	pub fn from_crcs(crcs: Vec<u32>) -> Self {
+		Self {
+			checksum: block_checksum(&crcs),
+			crcs,
+		}
+	}
+
+	// the feed is random per map load, the server sends it in the gamestate
+	pub fn pure_checksum(&self, checksum_feed: i32) -> i32 {
+		let mut longs: Vec<u32> = Vec::with_capacity(self.crcs.len() + 1);
+		longs.push(checksum_feed as u32);
+		longs.extend(&self.crcs);
+
+		block_checksum(&longs)
+	}
+}
+
+// a pk3 as servers name it, sv_referencedPakNames uses gamename/name, sv_pakNames just the name
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PakChecksum {
+	pub path: String,
+	pub gamename: String,
+	pub name: String,
+	pub checksum: i32,
+	pub pure_checksum: Option<i32>,
+}
+
+// every pk3 the game would open, in search order
+pub fn pak_checksums(vfs: &Vfs, checksum_feed: Option<i32>) -> Vec<PakChecksum> {
+	let sources: Vec<usize> = (0..vfs.sources.len())
+		.filter(|s| vfs.sources[*s].kind == SourceKind::Pk3)
+		.collect();
+
+	sources
+		.into_par_iter()
+		.filter_map(|s| {
+			let source = &vfs.sources[s];
+			let checksum = vfs.pk3_checksum(s)?;
+			let gamename = Path::new(&vfs.search_paths[source.search_path]).file_name()?.to_string_lossy().to_string();
+
+			Some(PakChecksum {
+				path: source.path.to_string_lossy().to_string(),
+				gamename,
+				name: source.path.file_stem()?.to_string_lossy().to_string(),
+				checksum: checksum.checksum,
+				pure_checksum: checksum_feed.map(|feed| checksum.pure_checksum(feed)),
+			})
+		})
+		.collect()
+}
+
+// Com_BlockChecksum, the four md4 digest words xored together
+fn block_checksum(longs: &[u32]) -> i32 {
+	let data: Vec<u8> = longs.iter().flat_map(|l| l.to_le_bytes()).collect();
+	let digest = md4(&data);
+
+	(digest[0] ^ digest[1] ^ digest[2] ^ digest[3]) as i32
+}
+
+// rfc 1320, the digest as four little endian words
+fn md4(data: &[u8]) -> [u32; 4] {
+	let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+	let mut message = data.to_vec();
+	message.push(0x80);
+
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+
+	message.extend(((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+	let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+	let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+	let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+	for block in message.chunks_exact(64) {
+		let x: Vec<u32> = block.chunks_exact(4).map(|w| u32::from_le_bytes(w.try_into().unwrap())).collect();
+		let [mut a, mut b, mut c, mut d] = state;
+
+		for i in [0, 4, 8, 12] {
+			a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+			d = d.wrapping_add(f(a, b, c)).wrapping_add(x[i + 1]).rotate_left(7);
+			c = c.wrapping_add(f(d, a, b)).wrapping_add(x[i + 2]).rotate_left(11);
+			b = b.wrapping_add(f(c, d, a)).wrapping_add(x[i + 3]).rotate_left(19);
+		}
+
+		for i in [0, 1, 2, 3] {
+			a = a.wrapping_add(g(b, c, d)).wrapping_add(x[i]).wrapping_add(0x5a827999).rotate_left(3);
+			d = d.wrapping_add(g(a, b, c)).wrapping_add(x[i + 4]).wrapping_add(0x5a827999).rotate_left(5);
+			c = c.wrapping_add(g(d, a, b)).wrapping_add(x[i + 8]).wrapping_add(0x5a827999).rotate_left(9);
+			b = b.wrapping_add(g(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x5a827999).rotate_left(13);
+		}
+
+		for i in [0, 2, 1, 3] {
+			a = a.wrapping_add(h(b, c, d)).wrapping_add(x[i]).wrapping_add(0x6ed9eba1).rotate_left(3);
+			d = d.wrapping_add(h(a, b, c)).wrapping_add(x[i + 8]).wrapping_add(0x6ed9eba1).rotate_left(9);
+			c = c.wrapping_add(h(d, a, b)).wrapping_add(x[i + 4]).wrapping_add(0x6ed9eba1).rotate_left(11);
+			b = b.wrapping_add(h(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x6ed9eba1).rotate_left(15);
+		}
+
+		state[0] = state[0].wrapping_add(a);
+		state[1] = state[1].wrapping_add(b);
+		state[2] = state[2].wrapping_add(c);
+		state[3] = state[3].wrapping_add(d);
+	}
+
+	state
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs::{remove_file, File};
+	use std::io::Write;
+	use std::path::Path;
+	use zip::write::SimpleFileOptions;
+	use zip::ZipWriter;
+
+	use super::{block_checksum, md4, Pk3Checksum};
+	use crate::vfs::{SourceKind, VfsReader, VfsSource};
+
+	fn md4_hex(data: &[u8]) -> String {
+		md4(data).iter().flat_map(|w| w.to_le_bytes()).map(|b| format!("{:02x}", b)).collect()
+	}
+
+	fn open_pk3(path: &Path) -> VfsReader {
+		VfsReader::open(&VfsSource { path: path.to_path_buf(), kind: SourceKind::Pk3, search_path: 0 }).unwrap()
+	}
+
+	#[test]
+	fn md4_matches_rfc_1320() {
+		assert_eq!(md4_hex(b""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+		assert_eq!(md4_hex(b"a"), "bde52cb31de33e46245e05fbdbd6fb24");
+		assert_eq!(md4_hex(b"abc"), "a448017aaf21d8525fc10ae87aa6729d");
+		assert_eq!(md4_hex(b"message digest"), "d9130a8164549fe818874806e1c7014b");
+		assert_eq!(md4_hex(b"abcdefghijklmnopqrstuvwxyz"), "d79e1c308aa5bbcdeea8ed63df412da9");
+		assert_eq!(
+			md4_hex(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+			"043f8582f241db351ce627e153e7f0e4"
+		);
+		assert_eq!(md4_hex("1234567890".repeat(8).as_bytes()), "e33b4ddc9c38f2199c3e7b164fcc0536");
+	}
+
+	// the rfc's 80 byte vector read as 20 longs, its digest words xored and printed signed
+	#[test]
+	fn block_checksum_xors_the_digest_words() {
+		let longs: Vec<u32> = "1234567890".repeat(8).as_bytes().chunks_exact(4).map(|l| u32::from_le_bytes(l.try_into().unwrap())).collect();
+
+		assert_eq!(block_checksum(&longs), -440274516);
+	}
+
+	#[test]
+	fn pk3_checksum_skips_empty_files_and_folders() {
+		let path = std::env::temp_dir().join(format!("pk3_checksum_{}.pk3", std::process::id()));
+		let mut zip = ZipWriter::new(File::create(&path).unwrap());
+
+		zip.add_directory("maps/", SimpleFileOptions::default()).unwrap();
+		zip.start_file("maps/a.bsp", SimpleFileOptions::default()).unwrap();
+		zip.write_all(b"first").unwrap();
+		zip.start_file("empty.cfg", SimpleFileOptions::default()).unwrap();
+		zip.start_file("scripts/b.shader", SimpleFileOptions::default()).unwrap();
+		zip.write_all(b"second").unwrap();
+		zip.finish().unwrap();
+
+		let checksum = open_pk3(&path).pk3_checksum();
+		remove_file(&path).unwrap();
+
+		// crc32 of "first" and "second"
+		let crcs = vec![0x9271ee57, 0xb61f1169];
+		assert_eq!(checksum.unwrap().checksum, Pk3Checksum::from_crcs(crcs).checksum);
+	}
+
+	/*
+	    the retail 1.32 pak0.pk3 is 1566731103 in sv_paks, it can't ship with the tests
+	    run with Q3_PAK0 set to its path and --ignored to check against it
+	*/
+	#[test]
+	#[ignore]
+	fn retail_pak0_checksum() {
+		let path = std::env::var("Q3_PAK0").expect("Q3_PAK0 should be the path to a 1.32 pak0.pk3");
+
+		assert_eq!(open_pk3(Path::new(&path)).pk3_checksum().unwrap().checksum, 1566731103);
+	}
+}
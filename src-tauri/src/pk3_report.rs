@@ -0,0 +1,119 @@
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::vfs::{SourceKind, Vfs, VfsFile};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileOverride {
+	pub name: String,
+	pub loaded_from: String,
+	// every other copy, highest priority first
+	pub overridden: Vec<String>,
+	// all copies are packed with the same crc and size, so which one loads makes no difference
+	pub identical: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pk3Overrides {
+	pub path: String,
+	pub files: usize,
+	// files in this pk3 the game loads from somewhere else
+	pub overridden: usize,
+	// files in this pk3 that hide another copy
+	pub overrides: usize,
+	// nothing in it is ever loaded
+	pub fully_shadowed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OverrideReport {
+	pub files: Vec<FileOverride>,
+	// in search order, only pk3s that override or are overridden
+	pub pk3s: Vec<Pk3Overrides>,
+}
+
+/*
+    every file with more than one copy in the client's search paths and which copy wins, see Vfs for the search order
+    loose files count as copies too since they override pk3s, they're never reported as identical
+    crcs come from the zip headers of the conflicting entries only, nothing is decompressed
+*/
+pub fn override_report(vfs: &Vfs) -> OverrideReport {
+	let mut report = OverrideReport::default();
+	let names: BTreeSet<String> = vfs.files().map(|f| f.name.to_lowercase()).collect();
+	let mut counts: HashMap<usize, (usize, usize, usize)> = HashMap::new();
+	let mut conflicts: Vec<Vec<&VfsFile>> = vec![];
+
+	for name in &names {
+		let copies: Vec<&VfsFile> = vfs.copies(name).collect();
+
+		for (i, file) in copies.iter().enumerate() {
+			let (files, overridden, overrides) = counts.entry(file.source).or_default();
+			*files += 1;
+
+			match i {
+				0 if copies.len() > 1 => *overrides += 1,
+				0 => (),
+				_ => *overridden += 1,
+			}
+		}
+
+		if copies.len() > 1 {
+			conflicts.push(copies);
+		}
+	}
+
+	let checksums = conflict_checksums(vfs, &conflicts);
+
+	for copies in conflicts {
+		let sums: Vec<Option<&(u32, u64)>> = copies.iter().map(|f| checksums.get(&(f.source, f.name.as_str()))).collect();
+
+		report.files.push(FileOverride {
+			name: copies[0].name.clone(),
+			loaded_from: vfs.disk_path(copies[0]).to_string_lossy().to_string(),
+			overridden: copies[1..].iter().map(|f| vfs.disk_path(f).to_string_lossy().to_string()).collect(),
+			identical: sums.iter().all(|s| s.is_some() && *s == sums[0]),
+		});
+	}
+
+	for (index, source) in vfs.sources.iter().enumerate() {
+		let Some((files, overridden, overrides)) = counts.get(&index) else { continue };
+
+		if source.kind == SourceKind::Pk3 && (*overridden > 0 || *overrides > 0) {
+			report.pk3s.push(Pk3Overrides {
+				path: source.path.to_string_lossy().to_string(),
+				files: *files,
+				overridden: *overridden,
+				overrides: *overrides,
+				fully_shadowed: overridden == files,
+			});
+		}
+	}
+
+	report
+}
+
+// each pk3 with a conflicting file is opened once
+fn conflict_checksums<'a>(vfs: &Vfs, conflicts: &[Vec<&'a VfsFile>]) -> HashMap<(usize, &'a str), (u32, u64)> {
+	let mut by_source: BTreeMap<usize, Vec<&'a VfsFile>> = BTreeMap::new();
+
+	for file in conflicts.iter().flatten() {
+		if vfs.source(file).kind == SourceKind::Pk3 {
+			by_source.entry(file.source).or_default().push(file);
+		}
+	}
+
+	let by_source: Vec<(usize, Vec<&'a VfsFile>)> = by_source.into_iter().collect();
+
+	by_source
+		.par_iter()
+		.flat_map_iter(|(source, files)| {
+			let mut reader = vfs.open(*source).inspect_err(|e| log::error!("{}: {}", vfs.sources[*source].path.to_string_lossy(), e)).ok();
+
+			files
+				.iter()
+				.filter_map(|f| reader.as_mut()?.checksum(&f.name).map(|sum| ((*source, f.name.as_str()), sum)))
+				.collect::<Vec<_>>()
+		})
+		.collect()
+}
@@ -1,38 +1,11 @@
-use crate::client::Q3ExecableConfig;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer};
 use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 use std::path::{Path, PathBuf};
 use std::fs::read_to_string;
-
-pub async fn get_q3_configs(dir: &Path) -> Result<Vec<Q3ExecableConfig>, std::io::Error> {
-	let mut q3_configs: Vec<Q3ExecableConfig> = vec![];
-
-	for entry in std::fs::read_dir(dir)? {
-		let entry = entry?;
-		let path = entry.path();
-
-		if path.is_dir() {
-			q3_configs.append(&mut Box::pin(get_q3_configs(&path)).await?);
-		}
-
-		if let Some(ext) = path.extension() {
-			let ext_s = ext.to_string_lossy().to_string();
-
-			let name = path.file_name();
-
-			if ext_s != "cfg" || name.is_none() {
-				continue;
-			}
-
-			let config_p = path.to_str().unwrap();
-
-			q3_configs.push(Q3ExecableConfig {
-				name: name.unwrap().to_str().unwrap().to_string(),
-				path: config_p.to_string(),
-			})
-		}
-	}
-	Ok(q3_configs)
-}
+use std::time::Duration;
 
 pub fn parse_colorstring(q3_string: &str) -> (String, String) {
 	let mut byte_pos: usize = 0;
@@ -226,3 +199,47 @@ pub fn read_q3config(q3config: &mut HashMap<String, HashMap<String, String>>, co
     }
     Ok(false)
 }
+
+// every connectionless packet starts with this, a packet that doesn't is netchan game traffic
+pub const OOB: &[u8] = b"\xff\xff\xff\xff";
+
+// accepts ip:port, [ipv6]:port and hostname:port, hostnames are resolved to their first address
+pub fn resolve_address(address: &str) -> Result<SocketAddr, std::io::Error> {
+    if let Ok(addr) = address.parse::<SocketAddr>() {
+        return Ok(addr)
+    }
+
+    address
+        .to_socket_addrs()?
+        .next()
+        .ok_or(Error::new(ErrorKind::NotFound, format!("could not resolve {}", address)))
+}
+
+// only ip:port, a hostname would block deserializing on DNS, they're resolved with resolve_address before querying
+pub fn deserialize_address<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SocketAddr, D::Error> {
+    let address = String::deserialize(deserializer)?;
+    address
+        .parse::<SocketAddr>()
+        .map_err(|_| serde::de::Error::custom(format!("{} is not an ip:port address", address)))
+}
+
+// IPv6 servers can only be reached from an IPv6 socket, binding fails on hosts without IPv6
+pub fn bind_query_socket(ipv6: bool, timeout: Duration) -> Result<UdpSocket, std::io::Error> {
+    let socket = UdpSocket::bind(if ipv6 { "[::]:0" } else { "0.0.0.0:0" })?;
+    socket.set_read_timeout(Some(timeout))?;
+    Ok(socket)
+}
+
+// * and ? wildcards, anchored and case insensitive, everything else is matched literally
+pub fn glob_regex(pattern: &str) -> Result<Regex, regex::Error> {
+	let expression: String = pattern
+		.chars()
+		.map(|c| match c {
+			'*' => String::from(".*"),
+			'?' => String::from("."),
+			_ => regex::escape(&c.to_string()),
+		})
+		.collect();
+
+	RegexBuilder::new(&format!("^{}$", expression)).case_insensitive(true).build()
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::q3_util::{bind_query_socket, OOB};
+
+const PRINT_RESPONSE: &[u8] = b"print\n";
+
+// how long to wait for more print packets once the first one arrived
+const RCON_STRAGGLER_TIMEOUT: Duration = Duration::from_millis(200);
+
+// a whole response, long output fits well within it, a server that never goes quiet is cut off here
+const RCON_MAX_READ_TIME: Duration = Duration::from_secs(5);
+
+const MAX_RCON_HISTORY: usize = 100;
+
+// not encryption, it only keeps the password from being readable at a glance in appdata.json
+const OBFUSCATION_KEY: &[u8] = b"sarge-launcher";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RconServer {
+	password: String,
+	pub history: Vec<String>,
+}
+
+impl RconServer {
+	pub fn password(&self) -> String {
+		deobfuscate(&self.password)
+	}
+
+	pub fn set_password(&mut self, password: &str) {
+		self.password = obfuscate(password);
+	}
+
+	pub fn has_password(&self) -> bool {
+		!self.password.is_empty()
+	}
+
+	// most recent last, a repeated command moves to the end instead of showing up twice
+	pub fn add_history(&mut self, command: &str) {
+		self.history.retain(|c| c != command);
+		self.history.push(command.to_string());
+
+		if self.history.len() > MAX_RCON_HISTORY {
+			self.history.remove(0);
+		}
+	}
+}
+
+fn obfuscate(plain: &str) -> String {
+	plain
+		.bytes()
+		.zip(OBFUSCATION_KEY.iter().cycle())
+		.map(|(b, k)| format!("{:02x}", b ^ k))
+		.collect()
+}
+
+fn deobfuscate(obfuscated: &str) -> String {
+	let bytes: Vec<u8> = (0..obfuscated.len())
+		.step_by(2)
+		.filter_map(|i| obfuscated.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+		.zip(OBFUSCATION_KEY.iter().cycle())
+		.map(|(b, k)| b ^ k)
+		.collect();
+
+	String::from_utf8_lossy(&bytes).to_string()
+}
+
+/*
+    rcon <password> <command> is answered with one or more print packets
+    the server flushes its redirect buffer every ~1000 bytes (SV_FlushRedirect) so long output
+    like status or cvarlist arrives split up, there's no end marker so read until the server goes quiet
+*/
+pub fn send_rcon(address: SocketAddr, password: &str, command: &str, timeout: Duration) -> Result<String, Error> {
+	if password.is_empty() {
+		return Err(Error::new(ErrorKind::InvalidInput, "no rcon password set for this server"));
+	}
+
+	if password.contains([' ', '"']) {
+		return Err(Error::new(ErrorKind::InvalidInput, "rcon password can't contain spaces or quotes"));
+	}
+
+	let socket: UdpSocket = bind_query_socket(address.is_ipv6(), timeout)?;
+
+	let mut packet = OOB.to_vec();
+	packet.extend(format!("rcon {} {}", password, command.trim()).bytes());
+	socket.send_to(&packet, address)?;
+
+	let output = read_print_response(&socket, address, timeout)?;
+
+	if output.starts_with("Bad rconpassword") {
+		return Err(Error::new(ErrorKind::PermissionDenied, "bad rcon password"));
+	}
+
+	if output.starts_with("No rconpassword set") {
+		return Err(Error::new(ErrorKind::PermissionDenied, "no rcon password set on the server"));
+	}
+
+	Ok(output)
+}
+
+fn read_print_response(socket: &UdpSocket, address: SocketAddr, timeout: Duration) -> Result<String, Error> {
+	let mut output: Vec<u8> = vec![];
+	let mut buf: [u8; 16384] = [0; 16384];
+	let mut last_packet: Option<Instant> = None;
+	let started = Instant::now();
+
+	// packets from elsewhere don't reset the wait, it only counts down from the start
+	loop {
+		let elapsed = started.elapsed();
+		let wait = match last_packet {
+			Some(_) => RCON_STRAGGLER_TIMEOUT.min(RCON_MAX_READ_TIME.saturating_sub(elapsed)),
+			None => timeout.min(RCON_MAX_READ_TIME).saturating_sub(elapsed),
+		};
+
+		if wait.is_zero() {
+			match last_packet {
+				Some(_) => break,
+				None => return Err(Error::new(ErrorKind::TimedOut, format!("no rcon response from {}", address))),
+			}
+		}
+
+		socket.set_read_timeout(Some(wait))?;
+
+		match socket.recv_from(&mut buf) {
+			Ok((bytes, from)) => {
+				let print = buf[..bytes].strip_prefix(OOB).and_then(|p| p.strip_prefix(PRINT_RESPONSE));
+
+				// a reply from anywhere else is not ours
+				match print {
+					Some(text) if from == address => output.extend(text),
+					_ => continue,
+				}
+				last_packet = Some(Instant::now());
+			}
+			Err(e) if last_packet.is_none() => {
+				return Err(Error::new(e.kind(), format!("no rcon response from {}: {}", address, e)));
+			}
+			Err(_) => break,
+		}
+	}
+
+	let output = String::from_utf8_lossy(&output);
+
+	Ok(output.trim_end_matches('\0').to_string())
+}
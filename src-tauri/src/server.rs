@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::io::{Error, ErrorKind};
-use std::net::UdpSocket;
+use std::net::{SocketAddr, UdpSocket};
 
 use std::time::Instant;
+use crate::info_string::InfoString;
 use crate::master::MasterServer;
 use crate::q3_util;
 use std::collections::HashMap;
@@ -11,10 +12,12 @@ const GETSTATUS: &[u8] = b"\xff\xff\xff\xffgetstatus\x00";
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ServerPlayer {
-	name: String,
-	namecolored: String,
+	pub name: String,
+	pub namecolored: String,
 	frags: i32,
 	ping: i32,
+	team: String,
+	spectator: bool,
 }
 
 impl ServerPlayer {
@@ -23,18 +26,30 @@ impl ServerPlayer {
 		Self {
 			name: parsed_name.0,
 			namecolored: parsed_name.1,
-			frags: frags.parse::<i32>().unwrap(),
-			ping: ping.parse::<i32>().unwrap(),
+			frags: frags.parse::<i32>().unwrap_or(0),
+			ping: ping.parse::<i32>().unwrap_or(0),
+			team: String::from("free"),
+			spectator: false,
 		}
 	}
+
+	fn set_team(&mut self, team: &str) {
+		self.team = team.to_owned();
+		self.spectator = team == "spectator";
+	}
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct TeamScores {
+	red: i32,
+	blue: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Quake3Server {
 	pub master: Option<MasterServer>,
-	pub ip: String,
-	pub port: String,
-	pub address: String,
+	#[serde(deserialize_with = "q3_util::deserialize_address")]
+	pub address: SocketAddr,
 	pub protocol: Option<u8>,
 	pub ping: u16,
 	pub errormessage: String,
@@ -47,18 +62,17 @@ pub struct Quake3Server {
 	pub map: String,
 	pub othersettings: HashMap<String, String>,
 	pub players: Option<Vec<ServerPlayer>>,
+	pub teamscores: Option<TeamScores>,
 	pub list: String,
 	pub custom: bool,
 	pub version: String,
 }
 
 impl Quake3Server {
-	pub fn new(ip: String, port: String, master: Option<MasterServer>, protocol: Option<u8>) -> Quake3Server {
+	pub fn new(address: SocketAddr, master: Option<MasterServer>, protocol: Option<u8>) -> Quake3Server {
 		Quake3Server {
 			master,
-			ip: ip.clone(),
-			port: port.clone(),
-			address: format!("{}:{}", ip, port),
+			address,
 			protocol: protocol,
 			ping: 0,
 			errormessage: String::from(""),
@@ -71,6 +85,7 @@ impl Quake3Server {
 			map: String::new(),
 			othersettings: HashMap::new(),
 			players: None,
+			teamscores: None,
 			list: String::from("main"),
 			custom: false,
 			version: String::from(""),
@@ -89,6 +104,7 @@ impl Quake3Server {
 		self.map = String::new();
 		self.othersettings = HashMap::new();
 		self.players = None;
+		self.teamscores = None;
 		self.list = String::from("main");
 		self.custom = false;
 		self.version = String::new();
@@ -102,7 +118,7 @@ impl Quake3Server {
             return
         }
 
-        match socket.send_to(GETSTATUS, self.address.to_owned()) {
+        match socket.send_to(GETSTATUS, self.address) {
             Ok(_bytes) => (),
             Err(err) => {
                 self.set_error(err);
@@ -135,7 +151,7 @@ impl Quake3Server {
             return Err(tauri::Error::FailedToReceiveMessage)
 		}
 
-		index += 20;
+		index += 19;
 
 		let mut reverse_response = response_buf;
 		reverse_response.reverse();
@@ -148,49 +164,40 @@ impl Quake3Server {
 
 		let end_index: usize = response_buf.len() - resp_end.unwrap();
 
-        let last_gamestate_end = reverse_response.iter().position(|&r| r == 0x5c);
+		let info_len = response_buf[index..].iter().position(|&r| r == 0x0a);
 
-        if last_gamestate_end.is_none() {
-            return Err(tauri::Error::AssetNotFound(String::from("last gamestate delimiter")))
+        if info_len.is_none() {
+            return Err(tauri::Error::AssetNotFound(String::from("server info end")))
         }
 
-		let last_gamestate_delimiter = response_buf.len() - last_gamestate_end.unwrap();
-
-		let last_gamestate_len= response_buf[last_gamestate_delimiter..].iter().position(|&r| r == 0x0a);
+		let info = InfoString::parse(&String::from_utf8_lossy(&response_buf[index..index + info_len.unwrap()]));
 
-        if last_gamestate_len.is_none() {
-            return Err(tauri::Error::AssetNotFound(String::from("last gamestate length")))
-        }
-
-		let gamestate = String::from_utf8_lossy(&response_buf[index..last_gamestate_delimiter + last_gamestate_len.unwrap()]);
-		let gamestate_list: Vec<&str> = gamestate.split("\\").collect();
-
-		for i in (0..gamestate_list.len()).step_by(2) {
-			match gamestate_list[i] {
+		for (key, value) in info.iter() {
+			match key.to_lowercase().as_str() {
 				"sv_hostname" => {
-					let parsed_host = q3_util::parse_colorstring(gamestate_list[i + 1]);
+					let parsed_host = q3_util::parse_colorstring(value);
 					self.host = parsed_host.0;
 					self.hostcolored = parsed_host.1;
 				}
 				"version" => {
-					self.version = gamestate_list[i + 1].to_owned();
+					self.version = value.to_owned();
 				}
 				"gamename" => {
-					self.game = gamestate_list[i + 1].to_owned();
+					self.game = value.to_owned();
 				}
 				"sv_maxclients" => {
-					self.maxclients = gamestate_list[i + 1].to_owned();
+					self.maxclients = value.to_owned();
 				}
 				"mapname" => {
-					self.map = gamestate_list[i + 1].to_owned();
+					self.map = value.to_owned();
 				}
 				_ => {
-					self.othersettings.entry(gamestate_list[i].to_owned()).or_insert(gamestate_list[i + 1].to_owned());
+					self.othersettings.entry(key.to_owned()).or_insert(value.to_owned());
 				}
 			}
 		}
 
-		index = last_gamestate_delimiter + last_gamestate_len.unwrap();
+		index += info_len.unwrap();
 
 		if index == end_index - 1 {
 			return Ok(());
@@ -198,18 +205,15 @@ impl Quake3Server {
 
 		let players = String::from_utf8_lossy(&response_buf[index + 1..end_index - 1]);
 		let player_list: Vec<&str> = players.split("\n").collect();
+		let mut team_columns: Vec<Option<String>> = vec![];
 
 		for player in player_list {
-			let p: Vec<&str> = player.split(" ").collect();
-			let name: String;
+			let (frags, ping, name, team_column) = match Self::split_player_line(player) {
+				Some(p) => p,
+				None => continue,
+			};
 
-			if p.len() > 3 {
-				name = p[2..].join(" ").to_string();
-			} else {
-				name = p[2].to_string();
-			}
-
-			let new_player = ServerPlayer::new(&name[1..name.len() - 1], p[0].to_owned(), p[1].to_owned());
+			let new_player = ServerPlayer::new(name, frags.to_owned(), ping.to_owned());
 
 			if new_player.ping == 0 {
 				self.bots += 1;
@@ -217,6 +221,8 @@ impl Quake3Server {
 				self.playersconnected += 1;
 			}
 
+			team_columns.push(team_column.map(String::from));
+
 			if let Some(player_vec) = self.players.as_mut() {
 				player_vec.push(new_player);
 			} else {
@@ -224,9 +230,126 @@ impl Quake3Server {
 			}
 		}
 
+		self.decode_teams(&info, &team_columns);
+
 		Ok(())
 	}
 
+	// frags ping "name" [team], the trailing column is only sent by some mods
+	fn split_player_line(line: &str) -> Option<(&str, &str, &str, Option<&str>)> {
+		let mut fields = line.splitn(3, ' ');
+		let frags = fields.next()?;
+		let ping = fields.next()?;
+		let rest = fields.next()?;
+
+		let name_start = rest.find('"')?;
+		let name_end = rest.rfind('"')?;
+
+		if name_end <= name_start {
+			return None;
+		}
+
+		let trailing = rest[name_end + 1..].split_whitespace().next();
+
+		Some((frags, ping, &rest[name_start + 1..name_end], trailing))
+	}
+
+	/*
+		team membership isn't part of the q3 status protocol, so each mod exposes it differently
+		OpenArena/CPMA/OSP: Players_Red and Players_Blue cvars, 1-based client slots
+		Urban Terror: g_redteamlist and g_blueteamlist, one letter per client slot starting at A
+		anything else that sends a team column after the player name uses the TEAM_* numbering
+		the status list is the occupied slots in slot order but without their numbers, so a slot is only known
+		when every listed player is on a team and the sorted slots line up with it, otherwise the lists are skipped
+		empty lists on a team game mean everyone listed is spectating
+	*/
+	fn decode_teams(&mut self, info: &InfoString, team_columns: &[Option<String>]) {
+		let players = match self.players.as_mut() {
+			Some(p) => p,
+			None => return,
+		};
+
+		let (red, blue) = match self.game.to_lowercase().as_str() {
+			"q3ut4" | "q3urt42" | "q3urt43" => (
+				Self::letter_team_list(info.get("g_redteamlist")),
+				Self::letter_team_list(info.get("g_blueteamlist")),
+			),
+			_ => (Self::numbered_team_list(info.get("Players_Red")), Self::numbered_team_list(info.get("Players_Blue"))),
+		};
+
+		let has_lists = info.contains_key("Players_Red") || info.contains_key("g_redteamlist");
+		let lists_empty = red.is_empty() && blue.is_empty();
+
+		let mut slots: Vec<usize> = red.iter().chain(&blue).copied().collect();
+		slots.sort();
+		slots.dedup();
+
+		if slots.len() != players.len() {
+			slots.clear();
+		}
+
+		let mut has_teams = !slots.is_empty();
+
+		for (i, player) in players.iter_mut().enumerate() {
+			let slot = slots.get(i);
+
+			if slot.is_some_and(|s| red.contains(s)) {
+				player.set_team("red");
+			} else if slot.is_some_and(|s| blue.contains(s)) {
+				player.set_team("blue");
+			} else if let Some(Some(column)) = team_columns.get(i) {
+				match column.as_str() {
+					"1" => player.set_team("red"),
+					"2" => player.set_team("blue"),
+					"3" => player.set_team("spectator"),
+					_ => continue,
+				}
+				has_teams = has_teams || player.team != "spectator";
+			} else if has_lists && lists_empty && Self::is_team_gametype(&self.game, info.get("g_gametype")) {
+				player.set_team("spectator");
+			}
+		}
+
+		let red_score = info.get("Score_Red").and_then(|s| s.trim().parse::<i32>().ok());
+		let blue_score = info.get("Score_Blue").and_then(|s| s.trim().parse::<i32>().ok());
+
+		if has_teams || red_score.is_some() || blue_score.is_some() {
+			self.teamscores = Some(TeamScores {
+				red: red_score.unwrap_or(0),
+				blue: blue_score.unwrap_or(0),
+			});
+		}
+	}
+
+	fn numbered_team_list(list: Option<&str>) -> Vec<usize> {
+		list.unwrap_or("").split_whitespace().filter_map(|n| n.parse::<usize>().ok()).filter(|n| *n > 0).map(|n| n - 1).collect()
+	}
+
+	fn letter_team_list(list: Option<&str>) -> Vec<usize> {
+		list.unwrap_or("").bytes().filter(|b| b.is_ascii_uppercase()).map(|b| (b - b'A') as usize).collect()
+	}
+
+	fn is_team_gametype(game: &str, gametype: Option<&str>) -> bool {
+		let gametype = gametype.and_then(|g| g.trim().parse::<u8>().ok()).unwrap_or(0);
+
+		match game.to_lowercase().as_str() {
+			"q3ut4" | "q3urt42" | "q3urt43" => (3..=8).contains(&gametype),
+			_ => gametype >= 3,
+		}
+	}
+
+	// any cvar from the status response, including the ones pulled out into their own fields
+	pub fn cvar(&self, key: &str) -> Option<&str> {
+		match key.to_lowercase().as_str() {
+			"sv_hostname" => Some(&self.host),
+			"version" => Some(&self.version),
+			"gamename" => Some(&self.game),
+			"sv_maxclients" => Some(&self.maxclients),
+			"mapname" => Some(&self.map),
+			_ => self.othersettings.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.as_str()),
+		}
+	}
+
 	pub fn set_error(&mut self, err: std::io::Error) -> () {
 		self.ping = 999;
 		self.errormessage = err.to_string();
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{read_to_string, rename, File};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::master::MasterStatus;
+use crate::server::Quake3Server;
+use crate::server_history::unix_time;
+
+/*
+    the last master results and refreshed servers, shown at startup before the first refresh finishes
+    stale is set whenever it's read back, the frontend clears it once it has refreshed for real
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ServerListCache {
+	pub saved_at: u64,
+	#[serde(default)]
+	pub stale: bool,
+	pub masters: Vec<MasterStatus>,
+	pub servers: Vec<Quake3Server>,
+}
+
+impl ServerListCache {
+	pub fn write_to_file(&mut self, path: &PathBuf) -> Result<(), tauri::Error> {
+		self.saved_at = unix_time();
+		self.stale = false;
+
+		// through a temp file so a crash mid write leaves the old cache, not a truncated one
+		let tmp_path = path.with_extension("json.tmp");
+		let mut file = File::create(&tmp_path)?;
+		let cache_string = serde_json::to_string(&self)?;
+		file.write_all(cache_string.as_bytes())?;
+		drop(file);
+		rename(&tmp_path, path)?;
+
+		Ok(())
+	}
+
+	// a cache that fails to parse was written by an older version, start over instead of failing startup
+	pub fn read_from_file(path: &PathBuf) -> Self {
+		let cache: Option<Self> = read_to_string(path).ok().and_then(|c| serde_json::from_str(&c).ok());
+
+		match cache {
+			Some(mut cache) => {
+				cache.stale = true;
+				cache
+			}
+			None => Self::default(),
+		}
+	}
+}
+
+/*
+    a refresh can cover only some servers, refresh_by_mod or the dashboard's pinned ones
+    refreshed servers replace the ones with their address and new ones are added, the rest are kept as they were
+*/
+pub fn merge_servers(servers: &mut Vec<Quake3Server>, refreshed: &[Quake3Server]) {
+	let mut positions: HashMap<SocketAddr, usize> = servers.iter().enumerate().map(|(i, s)| (s.address, i)).collect();
+
+	for server in refreshed {
+		match positions.get(&server.address) {
+			Some(i) => servers[*i] = server.clone(),
+			None => {
+				positions.insert(server.address, servers.len());
+				servers.push(server.clone());
+			}
+		}
+	}
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::server::Quake3Server;
+use crate::vfs::{SourceKind, Vfs};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PakState {
+	Ok,
+	Missing,
+	// a pk3 of that name is there but with another checksum, the game won't download over it
+	Mismatch,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerPak {
+	// gamename/name like sv_referencedPakNames
+	pub name: String,
+	pub checksum: i32,
+	pub state: PakState,
+	pub local_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerCompatibility {
+	pub fs_game: String,
+	pub has_mod: bool,
+	pub map: String,
+	pub has_map: bool,
+	pub pure: bool,
+	// empty when the server doesn't put its paks in the status response
+	pub paks: Vec<ServerPak>,
+	// g_needpass, joining is pointless without the password
+	pub needs_password: bool,
+	pub private_slots: u8,
+	// only the sv_privateClients slots are left, they take the private password
+	pub public_slots_full: bool,
+	pub version: String,
+	// what would go wrong joining, empty when nothing should
+	pub problems: Vec<String>,
+}
+
+/*
+    what a client is missing to join a server, from the server's status cvars and the vfs for its fs_game
+    sv_paks, sv_referencedPaks and their name lists are systeminfo cvars, only some servers repeat them in getstatus
+    a referenced pak is there when any local pk3 has its checksum, the same check FS_ComparePaks does
+    the pk3 named like it is checksummed first, the others only when that one doesn't match
+    on pure servers the map only counts if its pk3 is one of sv_paks
+    private slots need sv_privatePassword once the public ones are taken, bots take slots like players do
+*/
+pub fn check_compatibility(server: &Quake3Server, fs_game: &str, vfs: &Vfs) -> ServerCompatibility {
+	let pure = server.cvar("sv_pure").is_some_and(|p| p.trim() == "1");
+
+	let pk3s: Vec<usize> = (0..vfs.sources.len()).filter(|s| vfs.sources[*s].kind == SourceKind::Pk3).collect();
+
+	let has_mod = vfs.search_paths.iter().any(|p| Path::new(p).file_name().is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case(fs_game)));
+
+	let paks: Vec<ServerPak> = split_list(server.cvar("sv_referencedPaks"))
+		.zip(split_list(server.cvar("sv_referencedPakNames")))
+		.filter_map(|(checksum, name)| Some((checksum.parse::<i32>().ok()?, name)))
+		.map(|(checksum, name)| {
+			let same_name = pk3s.iter().find(|s| pak_name(vfs, **s).eq_ignore_ascii_case(name)).copied();
+			let same_checksum = same_name
+				.filter(|s| vfs.pk3_checksum(*s).is_some_and(|c| c.checksum == checksum))
+				.or_else(|| pk3s.iter().find(|s| vfs.pk3_checksum(**s).is_some_and(|c| c.checksum == checksum)).copied());
+
+			let (state, local) = match (same_checksum, same_name) {
+				(Some(source), _) => (PakState::Ok, Some(source)),
+				(None, Some(source)) => (PakState::Mismatch, Some(source)),
+				(None, None) => (PakState::Missing, None),
+			};
+
+			ServerPak {
+				name: name.to_string(),
+				checksum,
+				state,
+				local_path: local.map(|s| vfs.sources[s].path.to_string_lossy().to_string()),
+			}
+		})
+		.collect();
+
+	let server_paks: HashSet<i32> = split_list(server.cvar("sv_paks")).filter_map(|c| c.parse::<i32>().ok()).collect();
+
+	let has_map = vfs.copies(&format!("maps/{}.bsp", server.map)).any(|bsp| {
+		if !pure || server_paks.is_empty() {
+			return true;
+		}
+
+		vfs.source(bsp).kind == SourceKind::Pk3 && vfs.pk3_checksum(bsp.source).is_some_and(|c| server_paks.contains(&c.checksum))
+	});
+
+	let private_slots = server.cvar("sv_privateClients").and_then(|p| p.trim().parse::<u8>().ok()).unwrap_or(0);
+	let maxclients = server.maxclients.parse::<u8>().unwrap_or(0);
+	let clients = server.playersconnected.saturating_add(server.bots);
+	let public_slots_full = private_slots > 0 && clients >= maxclients.saturating_sub(private_slots) && clients < maxclients;
+	let needs_password = server.cvar("g_needpass").is_some_and(|p| p.trim() == "1");
+
+	let mut problems: Vec<String> = vec![];
+
+	if !has_mod {
+		problems.push(format!("{} is not installed", fs_game));
+	}
+
+	if !has_map {
+		problems.push(match pure && !server_paks.is_empty() {
+			true => format!("no pk3 the pure server allows has {}", server.map),
+			false => format!("{} is missing", server.map),
+		});
+	}
+
+	for pak in &paks {
+		match pak.state {
+			PakState::Missing => problems.push(format!("{}.pk3 is missing", pak.name)),
+			PakState::Mismatch => problems.push(format!("{}.pk3 is a different version than the server's", pak.name)),
+			PakState::Ok => (),
+		}
+	}
+
+	if needs_password {
+		problems.push(String::from("a password is needed"));
+	}
+
+	if public_slots_full {
+		problems.push(String::from("only private slots are free, they need the private password"));
+	}
+
+	ServerCompatibility {
+		fs_game: fs_game.to_string(),
+		has_mod,
+		map: server.map.clone(),
+		has_map,
+		pure,
+		paks,
+		needs_password,
+		private_slots,
+		public_slots_full,
+		version: server.version.clone(),
+		problems,
+	}
+}
+
+// gamename/name like sv_referencedPakNames lists it
+fn pak_name(vfs: &Vfs, source: usize) -> String {
+	let source = &vfs.sources[source];
+	let gamename = Path::new(&vfs.search_paths[source.search_path]).file_name().unwrap_or_default().to_string_lossy();
+
+	format!("{}/{}", gamename, source.path.file_stem().unwrap_or_default().to_string_lossy())
+}
+
+fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+	list.unwrap_or_default().split_whitespace()
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{read_to_string, rename, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::server::Quake3Server;
+
+// samples older than this are dropped when the file gets compacted and skipped when it's read
+const HISTORY_RETENTION: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+const MAX_HISTORY_FILE_SIZE: u64 = 32 * 1024 * 1024;
+// compacting trims to this so the next few refreshes don't compact again
+const COMPACTED_HISTORY_FILE_SIZE: u64 = 24 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerSample {
+	pub address: String,
+	pub time: u64,
+	pub players: u8,
+	pub bots: u8,
+	pub map: String,
+	pub ping: u16,
+	pub reachable: bool,
+}
+
+impl ServerSample {
+	pub fn from_server(server: &Quake3Server, time: u64) -> Self {
+		let reachable = server.errormessage.is_empty();
+
+		Self {
+			address: server.address.to_string(),
+			time,
+			players: server.playersconnected,
+			bots: server.bots,
+			map: if reachable { server.map.to_lowercase() } else { String::new() },
+			ping: server.ping,
+			reachable,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ServerStats {
+	pub address: String,
+	pub samples: usize,
+	pub first_seen: Option<u64>,
+	pub last_seen_online: Option<u64>,
+	pub uptime: f32,
+	pub average_players: f32,
+	pub typical_players: u8,
+	pub peak_players: u8,
+	pub hourly_players: Vec<f32>,
+	pub peak_hours: Vec<u8>,
+	pub maps: Vec<(String, usize)>,
+}
+
+/*
+    one json line per server per refresh, appending keeps a refresh of a few thousand servers cheap
+    queries read the whole file, it's compacted once it grows past MAX_HISTORY_FILE_SIZE
+*/
+pub fn record(path: &Path, servers: &[Quake3Server]) -> Result<(), std::io::Error> {
+	let now = unix_time();
+
+	let file = OpenOptions::new().create(true).append(true).open(path)?;
+	let mut writer = BufWriter::new(file);
+
+	for server in servers.iter().filter(|s| s.list != "trash") {
+		serde_json::to_writer(&mut writer, &ServerSample::from_server(server, now))?;
+		writer.write_all(b"\n")?;
+	}
+	writer.flush()?;
+
+	if std::fs::metadata(path)?.len() > MAX_HISTORY_FILE_SIZE {
+		compact(path, now.saturating_sub(HISTORY_RETENTION.as_secs()))?;
+	}
+
+	Ok(())
+}
+
+// compacting only happens once the file is big, samples past HISTORY_RETENTION can still be in it
+pub fn load(path: &Path, address: &str, since: u64) -> Result<Vec<ServerSample>, std::io::Error> {
+	if !path.exists() {
+		return Ok(vec![]);
+	}
+
+	let since = since.max(unix_time().saturating_sub(HISTORY_RETENTION.as_secs()));
+
+	let samples = read_to_string(path)?
+		.lines()
+		.filter(|line| line.contains(address))
+		.filter_map(|line| serde_json::from_str::<ServerSample>(line).ok())
+		.filter(|s| s.address == address && s.time >= since)
+		.collect();
+
+	Ok(samples)
+}
+
+/*
+    hours are shifted by the caller's utc offset so peak hours come back in the user's local time
+    typical_players is the median of the samples where the server answered
+*/
+pub fn stats(address: &str, samples: &[ServerSample], utc_offset_minutes: i32) -> ServerStats {
+	let mut stats = ServerStats {
+		address: address.to_string(),
+		samples: samples.len(),
+		first_seen: samples.iter().map(|s| s.time).min(),
+		last_seen_online: samples.iter().filter(|s| s.reachable).map(|s| s.time).max(),
+		hourly_players: vec![0.0; 24],
+		..Default::default()
+	};
+
+	let online: Vec<&ServerSample> = samples.iter().filter(|s| s.reachable).collect();
+
+	if online.is_empty() {
+		return stats;
+	}
+
+	stats.uptime = online.len() as f32 / samples.len() as f32;
+	stats.average_players = online.iter().map(|s| s.players as f32).sum::<f32>() / online.len() as f32;
+	stats.peak_players = online.iter().map(|s| s.players).max().unwrap_or(0);
+
+	let mut players: Vec<u8> = online.iter().map(|s| s.players).collect();
+	players.sort_unstable();
+	stats.typical_players = players[players.len() / 2];
+
+	let mut hour_samples: Vec<usize> = vec![0; 24];
+
+	for s in &online {
+		let local_time = s.time as i64 + utc_offset_minutes as i64 * 60;
+		let hour = local_time.rem_euclid(86400) / 3600;
+		stats.hourly_players[hour as usize] += s.players as f32;
+		hour_samples[hour as usize] += 1;
+	}
+
+	for (total, count) in stats.hourly_players.iter_mut().zip(&hour_samples) {
+		if *count > 0 {
+			*total /= *count as f32;
+		}
+	}
+
+	let busiest = stats.hourly_players.iter().cloned().fold(0.0, f32::max);
+
+	// every hour that gets within 80% of the busiest one, so a flat evening shows up as a range
+	if busiest > 0.0 {
+		stats.peak_hours = (0..24).filter(|h| stats.hourly_players[*h as usize] >= busiest * 0.8).collect();
+	}
+
+	let mut maps: HashMap<&str, usize> = HashMap::new();
+
+	for s in &online {
+		*maps.entry(s.map.as_str()).or_default() += 1;
+	}
+
+	let mut maps: Vec<(String, usize)> = maps.into_iter().map(|(m, c)| (m.to_string(), c)).collect();
+	maps.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+	maps.truncate(5);
+	stats.maps = maps;
+
+	stats
+}
+
+// drops samples older than since, then the oldest ones left until the file is under COMPACTED_HISTORY_FILE_SIZE
+fn compact(path: &Path, since: u64) -> Result<(), std::io::Error> {
+	let contents = read_to_string(path)?;
+	let kept: Vec<&str> = contents
+		.lines()
+		.filter(|line| serde_json::from_str::<ServerSample>(line).is_ok_and(|sample| sample.time >= since))
+		.collect();
+
+	// lines are appended in time order, so the oldest are at the front
+	let mut size: u64 = kept.iter().map(|line| line.len() as u64 + 1).sum();
+	let mut first = 0;
+
+	while size > COMPACTED_HISTORY_FILE_SIZE && first < kept.len() {
+		size -= kept[first].len() as u64 + 1;
+		first += 1;
+	}
+
+	let tmp_path = path.with_extension("jsonl.tmp");
+	let mut writer = BufWriter::new(File::create(&tmp_path)?);
+
+	for line in &kept[first..] {
+		writer.write_all(line.as_bytes())?;
+		writer.write_all(b"\n")?;
+	}
+
+	writer.flush()?;
+	drop(writer);
+	rename(&tmp_path, path)?;
+
+	Ok(())
+}
+
+pub fn unix_time() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
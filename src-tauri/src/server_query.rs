@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+use crate::q3_util::glob_regex;
+use crate::server::Quake3Server;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortColumn {
+	Game,
+	Host,
+	Map,
+	Playersconnected,
+	Ping,
+	Address,
+}
+
+/*
+    every field is optional, an empty query returns the servers as they are
+    players counts humans and bots, or only humans when exclude_bots is set, the players sort counts the same way
+    map is a * and ? glob, search is the browser's search box and matches any part of the map, game, hostname or address
+    game, gametype and cvars compare case insensitive, version matches any part of the version string
+*/
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ServerQuery {
+	pub search: Option<String>,
+	pub game: Option<String>,
+	pub map: Option<String>,
+	pub gametype: Option<String>,
+	pub min_players: Option<u8>,
+	pub max_players: Option<u8>,
+	pub exclude_bots: bool,
+	pub max_ping: Option<u16>,
+	pub version: Option<String>,
+	pub has_password: Option<bool>,
+	pub cvars: HashMap<String, String>,
+	pub include_unreachable: bool,
+	pub sort: Option<SortColumn>,
+	pub descending: bool,
+}
+
+impl ServerQuery {
+	pub fn apply<'a>(&self, servers: &'a [Quake3Server]) -> Result<Vec<&'a Quake3Server>, Error> {
+		let map_glob = match &self.map {
+			Some(m) if !m.is_empty() => {
+				Some(glob_regex(m).map_err(|e| Error::new(ErrorKind::InvalidInput, format!("bad map filter {}: {}", m, e)))?)
+			}
+			_ => None,
+		};
+
+		let search = self.search.as_ref().map(|s| s.to_lowercase()).filter(|s| !s.is_empty());
+
+		let mut filtered: Vec<&'a Quake3Server> = servers
+			.iter()
+			.filter(|s| self.include_unreachable || s.errormessage.is_empty())
+			.filter(|s| map_glob.as_ref().is_none_or(|g| g.is_match(&s.map)))
+			.filter(|s| search.as_ref().is_none_or(|q| Self::search_matches(s, q)))
+			.filter(|s| self.matches(s))
+			.collect();
+
+		if let Some(column) = self.sort {
+			filtered.sort_by(|a, b| {
+				let order = self.compare(column, a, b);
+				if self.descending { order.reverse() } else { order }
+			});
+		}
+
+		Ok(filtered)
+	}
+
+	// bots are never counted in playersconnected, see parse_status_response
+	fn players(&self, server: &Quake3Server) -> u8 {
+		if self.exclude_bots {
+			server.playersconnected
+		} else {
+			server.playersconnected.saturating_add(server.bots)
+		}
+	}
+
+	fn search_matches(server: &Quake3Server, search: &str) -> bool {
+		[&server.map, &server.game, &server.host].iter().any(|f| f.to_lowercase().contains(search))
+			|| server.address.to_string().contains(search)
+	}
+
+	fn matches(&self, server: &Quake3Server) -> bool {
+		let players = self.players(server);
+
+		let equals = |value: Option<&str>, wanted: &Option<String>| match wanted {
+			Some(w) if !w.is_empty() => value.is_some_and(|v| v.eq_ignore_ascii_case(w)),
+			_ => true,
+		};
+
+		equals(Some(&server.game), &self.game)
+			&& equals(server.cvar("g_gametype"), &self.gametype)
+			&& self.min_players.is_none_or(|min| players >= min)
+			&& self.max_players.is_none_or(|max| players <= max)
+			&& self.max_ping.is_none_or(|max| server.ping <= max)
+			&& self.version.as_ref().is_none_or(|v| server.version.to_lowercase().contains(&v.to_lowercase()))
+			&& self.has_password.is_none_or(|p| (server.cvar("g_needpass") == Some("1")) == p)
+			&& self.cvars.iter().all(|(k, v)| server.cvar(k).is_some_and(|c| c.eq_ignore_ascii_case(v)))
+	}
+
+	fn compare(&self, column: SortColumn, a: &Quake3Server, b: &Quake3Server) -> Ordering {
+		match column {
+			SortColumn::Game => a.game.cmp(&b.game),
+			SortColumn::Host => a.host.cmp(&b.host),
+			SortColumn::Map => a.map.cmp(&b.map),
+			SortColumn::Playersconnected => self.players(a).cmp(&self.players(b)),
+			SortColumn::Ping => a.ping.cmp(&b.ping),
+			SortColumn::Address => a.address.cmp(&b.address),
+		}
+	}
+}
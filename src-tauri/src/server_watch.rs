@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::q3_util::glob_regex;
+use crate::server::Quake3Server;
+use crate::watchlist::WatchedPlayer;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WatchCondition {
+	Online,
+	SlotFree,
+	MinPlayers { count: u8 },
+	Map { name: String },
+	PlayerJoined { player: WatchedPlayer },
+}
+
+impl WatchCondition {
+	/*
+	    what currently satisfies the condition, a notification goes out for anything new since the last poll
+	    most conditions are either met or not, players joining are tracked by name so every new one is reported
+	*/
+	pub fn active(&self, server: &Quake3Server) -> HashSet<String> {
+		let mut active: HashSet<String> = HashSet::new();
+		let online = server.errormessage.is_empty();
+		let maxclients = server.maxclients.parse::<u8>().unwrap_or(0);
+
+		match self {
+			WatchCondition::Online if online => {
+				active.insert(String::new());
+			}
+			WatchCondition::SlotFree if online && server.playersconnected.saturating_add(server.bots) < maxclients => {
+				active.insert(String::new());
+			}
+			WatchCondition::MinPlayers { count } if online && server.playersconnected >= *count => {
+				active.insert(String::new());
+			}
+			WatchCondition::Map { name } if online && glob_regex(name).is_ok_and(|m| m.is_match(&server.map)) => {
+				active.insert(server.map.to_lowercase());
+			}
+			WatchCondition::PlayerJoined { player } if online => {
+				if let (Ok(matcher), Some(players)) = (player.matcher(), &server.players) {
+					active.extend(players.iter().filter(|p| matcher.is_match(&p.name)).map(|p| p.name.clone()));
+				}
+			}
+			_ => (),
+		}
+
+		active
+	}
+
+	pub fn message(&self, server: &Quake3Server, key: &str) -> String {
+		match self {
+			WatchCondition::Online => format!("{} is online", server.host),
+			WatchCondition::SlotFree => format!("a slot is free on {} ({}/{})", server.host, server.playersconnected.saturating_add(server.bots), server.maxclients),
+			WatchCondition::MinPlayers { count } => format!("{} has {} or more players", server.host, count),
+			WatchCondition::Map { .. } => format!("{} is playing {}", server.host, key),
+			WatchCondition::PlayerJoined { .. } => format!("{} joined {}", key, server.host),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerWatch {
+	pub address: String,
+	pub conditions: Vec<WatchCondition>,
+	// what each condition matched on the last poll, None until the first poll
+	#[serde(skip)]
+	last_active: Option<Vec<HashSet<String>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchEvent {
+	pub address: String,
+	pub host: String,
+	pub condition: WatchCondition,
+	pub message: String,
+}
+
+impl ServerWatch {
+	// the first poll only records what's already true, so starting a watch on a full server doesn't fire right away
+	pub fn check(&mut self, server: &Quake3Server) -> Vec<WatchEvent> {
+		let mut events: Vec<WatchEvent> = vec![];
+		let active: Vec<HashSet<String>> = self.conditions.iter().map(|c| c.active(server)).collect();
+
+		if let Some(last_active) = &self.last_active {
+			for ((condition, now), before) in self.conditions.iter().zip(&active).zip(last_active) {
+				for key in now.difference(before) {
+					events.push(WatchEvent {
+						address: self.address.clone(),
+						host: server.host.clone(),
+						condition: condition.clone(),
+						message: condition.message(server, key),
+					});
+				}
+			}
+		}
+
+		self.last_active = Some(active);
+
+		events
+	}
+}
+
+// the polling thread behind watch mode, dropping the handle stops it
+pub struct WatchHandle {
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+	pub fn spawn<F>(interval: Duration, mut poll: F) -> Self
+	where
+		F: FnMut() + Send + 'static,
+	{
+		let stop = Arc::new(AtomicBool::new(false));
+		let stopped = Arc::clone(&stop);
+
+		let thread = thread::spawn(move || {
+			while !stopped.load(Ordering::Relaxed) {
+				let started = Instant::now();
+				poll();
+
+				// sleep in short steps so stopping doesn't wait out a whole interval
+				while !stopped.load(Ordering::Relaxed) && started.elapsed() < interval {
+					thread::sleep(Duration::from_millis(250));
+				}
+			}
+		});
+
+		Self {
+			stop,
+			thread: Some(thread),
+		}
+	}
+
+	pub fn stop(mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for WatchHandle {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+	}
+}
@@ -0,0 +1,209 @@
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::vfs::{Vfs, VfsFile, VfsReader};
+
+// a shader from scripts/*.shader and the images its stages and sky load
+#[derive(Debug, Clone)]
+pub struct ShaderDef {
+	pub name: String,
+	pub images: Vec<String>,
+}
+
+/*
+    every shader name defined in the client's shader scripts, keyed without case
+    only the loaded copy of each .shader file is read, like ScanAndLoadShaderFiles in tr_shader.c
+    when two scripts define the same shader the one in the alphabetically last script is kept, whatever pk3 it came from
+    ScanAndLoadShaderFiles joins the scripts last to first and takes the first definition it finds
+*/
+pub struct ShaderIndex {
+	shaders: HashMap<String, (VfsFile, ShaderDef)>,
+}
+
+impl ShaderIndex {
+	pub fn build(vfs: &Vfs) -> Self {
+		let mut scripts: HashMap<String, &VfsFile> = HashMap::new();
+
+		for file in vfs.files() {
+			let name_lowered = file.name.to_lowercase();
+
+			if name_lowered.starts_with("scripts/") && name_lowered.ends_with(".shader") {
+				scripts.entry(name_lowered).or_insert(file);
+			}
+		}
+
+		let mut by_source: BTreeMap<usize, Vec<&VfsFile>> = BTreeMap::new();
+		for file in scripts.into_values() {
+			by_source.entry(file.source).or_default().push(file);
+		}
+
+		let by_source: Vec<(usize, Vec<&VfsFile>)> = by_source.into_iter().collect();
+
+		let parsed: Vec<Vec<(VfsFile, ShaderDef)>> = by_source
+			.par_iter()
+			.map(|(source, files)| {
+				let mut reader = match VfsReader::open(&vfs.sources[*source]) {
+					Ok(r) => r,
+					Err(e) => {
+						log::error!("Could not read shaders from {}: {}", vfs.sources[*source].path.to_string_lossy(), e);
+						return vec![];
+					}
+				};
+
+				let mut shaders: Vec<(VfsFile, ShaderDef)> = vec![];
+
+				for file in files {
+					match reader.read(&file.name) {
+						Ok(text) => shaders.extend(parse_shaders(&String::from_utf8_lossy(&text)).into_iter().map(|s| ((*file).clone(), s))),
+						Err(e) => log::error!("Could not read {}: {}", file.name, e),
+					}
+				}
+
+				shaders
+			})
+			.collect();
+
+		// stable, so within one script the first definition still wins
+		let mut parsed: Vec<(VfsFile, ShaderDef)> = parsed.into_iter().flatten().collect();
+		parsed.sort_by_key(|(script, _)| Reverse(script.name.to_lowercase()));
+
+		let mut shaders: HashMap<String, (VfsFile, ShaderDef)> = HashMap::new();
+
+		for (script, shader) in parsed {
+			shaders.entry(shader.name.to_lowercase()).or_insert((script, shader));
+		}
+
+		Self { shaders }
+	}
+
+	pub fn get(&self, name: &str) -> Option<&(VfsFile, ShaderDef)> {
+		self.shaders.get(&name.to_lowercase())
+	}
+}
+
+/*
+    shader name
+    {
+        skyparms env/sky - -
+        {
+            map textures/base/wall.tga
+            animMap 8 fire1.tga fire2.tga
+        }
+    }
+    keywords take the rest of their line, $lightmap, $whiteimage and *white are built into the renderer
+*/
+pub fn parse_shaders(text: &str) -> Vec<ShaderDef> {
+	let tokens = tokenize(text);
+	let mut shaders: Vec<ShaderDef> = vec![];
+	let mut current: Option<ShaderDef> = None;
+	let mut depth = 0;
+	let mut i = 0;
+
+	while i < tokens.len() {
+		let (line, token) = &tokens[i];
+
+		match token.as_str() {
+			"{" => depth += 1,
+			"}" => {
+				depth = (depth - 1).max(0);
+
+				if depth == 0 {
+					if let Some(shader) = current.take() {
+						shaders.push(shader);
+					}
+				}
+			}
+			name if depth == 0 => {
+				current = Some(ShaderDef {
+					name: name.to_string(),
+					images: vec![],
+				})
+			}
+			keyword => {
+				let args: Vec<&str> = tokens[i + 1..]
+					.iter()
+					.take_while(|(l, t)| l == line && t != "{" && t != "}")
+					.map(|(_, t)| t.as_str())
+					.collect();
+
+				if let Some(shader) = current.as_mut() {
+					shader.images.extend(keyword_images(keyword, &args, depth));
+				}
+
+				i += args.len();
+			}
+		}
+
+		i += 1;
+	}
+
+	shaders
+}
+
+fn keyword_images(keyword: &str, args: &[&str], depth: i32) -> Vec<String> {
+	let loaded = |image: &&&str| !image.starts_with('$') && !image.starts_with('*') && **image != "-";
+
+	match (depth, keyword.to_lowercase().as_str()) {
+		(2, "map") | (2, "clampmap") => args.first().filter(loaded).map(|i| vec![i.to_string()]).unwrap_or_default(),
+		(2, "animmap") => args.iter().skip(1).filter(loaded).map(|i| i.to_string()).collect(),
+		// farbox images are the base name with a suffix per side
+		(1, "skyparms") => args
+			.first()
+			.filter(loaded)
+			.map(|sky| ["rt", "bk", "lf", "ft", "up", "dn"].iter().map(|side| format!("{}_{}", sky, side)).collect())
+			.unwrap_or_default(),
+		_ => vec![],
+	}
+}
+
+// script tokens with the line they're on, comments are dropped and quoted strings kept whole
+fn tokenize(text: &str) -> Vec<(usize, String)> {
+	let mut tokens: Vec<(usize, String)> = vec![];
+	let mut chars = text.chars().peekable();
+	let mut line = 0;
+
+	while let Some(c) = chars.next() {
+		match c {
+			'\n' => line += 1,
+			c if c.is_whitespace() => continue,
+			'/' if chars.peek() == Some(&'/') => {
+				for c in chars.by_ref() {
+					if c == '\n' {
+						line += 1;
+						break;
+					}
+				}
+			}
+			'/' if chars.peek() == Some(&'*') => {
+				chars.next();
+				let mut last = ' ';
+
+				for c in chars.by_ref() {
+					if c == '\n' {
+						line += 1;
+					}
+
+					if last == '*' && c == '/' {
+						break;
+					}
+
+					last = c;
+				}
+			}
+			'"' => tokens.push((line, chars.by_ref().take_while(|c| *c != '"').collect())),
+			'{' | '}' => tokens.push((line, c.to_string())),
+			c => {
+				let mut token = String::from(c);
+
+				while let Some(next) = chars.next_if(|n| !n.is_whitespace() && !matches!(n, '{' | '}' | '"')) {
+					token.push(next);
+				}
+
+				tokens.push((line, token));
+			}
+		}
+	}
+
+	tokens
+}
@@ -0,0 +1,65 @@
+use std::io::Error;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/*
+    runs a handler for every packet received on the socket from a background thread
+    tick runs at least every POLL_INTERVAL for anything the service sends on its own
+    the thread checks for a stop every POLL_INTERVAL, dropping the handle stops it too
+*/
+pub struct UdpServiceHandle {
+	pub address: SocketAddr,
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl UdpServiceHandle {
+	pub fn spawn<T, F>(socket: UdpSocket, mut tick: T, mut handler: F) -> Result<Self, Error>
+	where
+		T: FnMut(&UdpSocket) + Send + 'static,
+		F: FnMut(&UdpSocket, &[u8], SocketAddr) + Send + 'static,
+	{
+		let address = socket.local_addr()?;
+		socket.set_read_timeout(Some(POLL_INTERVAL))?;
+
+		let stop = Arc::new(AtomicBool::new(false));
+		let stopped = Arc::clone(&stop);
+
+		let thread = thread::spawn(move || {
+			let mut buf: Vec<u8> = vec![0; 65507];
+
+			while !stopped.load(Ordering::Relaxed) {
+				tick(&socket);
+
+				if let Ok((bytes, from)) = socket.recv_from(&mut buf) {
+					handler(&socket, &buf[..bytes], from);
+				}
+			}
+		});
+
+		Ok(Self {
+			address,
+			stop,
+			thread: Some(thread),
+		})
+	}
+
+	pub fn stop(mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for UdpServiceHandle {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+	}
+}
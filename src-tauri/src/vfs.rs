@@ -0,0 +1,396 @@
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::io::{Error, ErrorKind, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use zip::ZipArchive;
+
+use crate::pk3_checksum::Pk3Checksum;
+use crate::shader::ShaderIndex;
+
+// (search path priority, loose/pk3, reverse pk3 name), lower ranks are searched first, see Vfs
+type SourceRank = (usize, u8, Reverse<String>);
+
+// folders in a search path the game writes demos, screenshots and video captures to
+const OUTPUT_DIRS: [&str; 3] = ["demos", "screenshots", "videos"];
+
+// a walked file with its modified time and size
+pub type FileStamp = (PathBuf, Option<SystemTime>, u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+	Dir,
+	Pk3,
+}
+
+// a search path directory for its loose files, or one pk3 inside it
+#[derive(Debug, Clone)]
+pub struct VfsSource {
+	pub path: PathBuf,
+	pub kind: SourceKind,
+	pub search_path: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct VfsFile {
+	// as stored, relative to the search path or pk3 root with / separators, e.g. maps/q3dm17.bsp
+	pub name: String,
+	pub source: usize,
+}
+
+/*
+    the game's view of its search paths, similar to FS_AddGameDirectory in qcommon/files.c
+    search paths come in priority order, inside one the loose files win over its pk3s,
+    pk3s are searched in reverse alphabetical order so zzz.pk3 overrides pak0.pk3
+    only pk3s directly in a search path are opened, one in a subfolder is just another loose file to the game
+    sources are kept sorted by that order so a lower source index always wins, lookups ignore case like the q3 filesystem does
+*/
+pub struct Vfs {
+	pub search_paths: Vec<String>,
+	pub sources: Vec<VfsSource>,
+	files: Vec<VfsFile>,
+	index: HashMap<String, Vec<usize>>,
+	// every file walked while building, to tell if the index is still current without opening any pk3
+	stamp: Vec<FileStamp>,
+	// shader scripts are only parsed the first time something asks for them
+	shaders: OnceLock<ShaderIndex>,
+	// per pk3 source, reading every entry's header is too slow to do for all of them up front
+	checksums: Mutex<HashMap<usize, Option<Pk3Checksum>>>,
+}
+
+impl Vfs {
+	pub fn build(search_paths: &[String]) -> Result<Self, Error> {
+		let listed = Self::list_sources(search_paths)?;
+
+		// only the central directory is read here, pk3 contents are pulled out later by whoever needs them
+		let opened: Vec<(VfsSource, Vec<String>)> = listed
+			.par_iter()
+			.filter_map(|(source, files)| {
+				let names = match source.kind {
+					SourceKind::Dir => loose_names(&source.path, files),
+					SourceKind::Pk3 => match VfsReader::open(source).and_then(|reader| reader.names()) {
+						Ok(names) => names,
+						Err(e) => {
+							log::error!("Could not read pk3, skipping: {}\n{}", &source.path.to_string_lossy(), e);
+							return None;
+						}
+					},
+				};
+
+				Some((source.clone(), names))
+			})
+			.collect();
+
+		let mut vfs = Self {
+			search_paths: search_paths.to_vec(),
+			sources: vec![],
+			files: vec![],
+			index: HashMap::new(),
+			stamp: listed.into_iter().flat_map(|(_, files)| files).collect(),
+			shaders: OnceLock::new(),
+			checksums: Mutex::new(HashMap::new()),
+		};
+
+		for (source_index, (source, names)) in opened.into_iter().enumerate() {
+			for name in names {
+				vfs.index.entry(name.to_lowercase()).or_default().push(vfs.files.len());
+				vfs.files.push(VfsFile { name, source: source_index });
+			}
+
+			vfs.sources.push(source);
+		}
+
+		Ok(vfs)
+	}
+
+	/*
+	    every source of the search paths in search order, only directories are read and no pk3 is opened
+	    a search path directory comes with its loose files, a pk3 with just itself
+	*/
+	pub fn list_sources(search_paths: &[String]) -> Result<Vec<(VfsSource, Vec<FileStamp>)>, Error> {
+		let mut ranked: Vec<(SourceRank, VfsSource, Vec<FileStamp>)> = vec![];
+
+		for (priority, p) in search_paths.iter().enumerate() {
+			let root = Path::new(p);
+
+			if !root.is_dir() {
+				continue;
+			}
+
+			let (pk3s, loose) = walk_search_path(root)?;
+
+			let source = VfsSource {
+				path: root.to_path_buf(),
+				kind: SourceKind::Dir,
+				search_path: priority,
+			};
+
+			ranked.push(((priority, 0, Reverse(String::new())), source, loose));
+
+			for pk3 in pk3s {
+				let pk3_name = pk3.0.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+
+				let source = VfsSource {
+					path: pk3.0.clone(),
+					kind: SourceKind::Pk3,
+					search_path: priority,
+				};
+
+				ranked.push(((priority, 1, Reverse(pk3_name)), source, vec![pk3]));
+			}
+		}
+
+		ranked.sort_by(|a, b| a.0.cmp(&b.0));
+
+		Ok(ranked.into_iter().map(|(_, source, files)| (source, files)).collect())
+	}
+
+	// walks the search paths again and compares names, sizes and modified times
+	pub fn is_current(&self, search_paths: &[String]) -> bool {
+		if self.search_paths != search_paths {
+			return false;
+		}
+
+		match Self::list_sources(search_paths) {
+			Ok(listed) => listed.into_iter().flat_map(|(_, files)| files).eq(self.stamp.iter().cloned()),
+			Err(_) => false,
+		}
+	}
+
+	// every file, highest priority source first
+	pub fn files(&self) -> impl Iterator<Item = &VfsFile> {
+		self.files.iter()
+	}
+
+	// the copy the game loads
+	pub fn lookup(&self, name: &str) -> Option<&VfsFile> {
+		self.copies(name).next()
+	}
+
+	// every copy of a file, the loaded one first
+	pub fn copies(&self, name: &str) -> impl Iterator<Item = &VfsFile> {
+		self.index.get(&name.to_lowercase()).into_iter().flatten().map(|i| &self.files[*i])
+	}
+
+	pub fn source(&self, file: &VfsFile) -> &VfsSource {
+		&self.sources[file.source]
+	}
+
+	// where a file lives on disk, the pk3 itself for anything packed
+	pub fn disk_path(&self, file: &VfsFile) -> PathBuf {
+		let source = self.source(file);
+
+		match source.kind {
+			SourceKind::Dir => source.path.join(&file.name),
+			SourceKind::Pk3 => source.path.clone(),
+		}
+	}
+
+	pub fn shaders(&self) -> &ShaderIndex {
+		self.shaders.get_or_init(|| ShaderIndex::build(self))
+	}
+
+	pub fn pk3_checksum(&self, source: usize) -> Option<Pk3Checksum> {
+		if let Some(checksum) = self.checksums.lock().unwrap().get(&source) {
+			return checksum.clone();
+		}
+
+		let checksum = match self.open(source) {
+			Ok(mut reader) => reader.pk3_checksum(),
+			Err(e) => {
+				log::error!("Could not read pk3 {}: {}", self.sources[source].path.to_string_lossy(), e);
+				None
+			}
+		};
+
+		self.checksums.lock().unwrap().insert(source, checksum.clone());
+
+		checksum
+	}
+
+	pub fn open(&self, source: usize) -> Result<VfsReader, Error> {
+		VfsReader::open(&self.sources[source])
+	}
+}
+
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+// reads files out of one source, a pk3 is only opened once however many files are read from it
+pub enum VfsReader {
+	Dir(PathBuf),
+	Pk3(ZipArchive<File>),
+}
+
+impl VfsReader {
+	pub fn open(source: &VfsSource) -> Result<Self, Error> {
+		match source.kind {
+			SourceKind::Dir => Ok(VfsReader::Dir(source.path.clone())),
+			SourceKind::Pk3 => Ok(VfsReader::Pk3(
+				ZipArchive::new(File::open(&source.path)?).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?,
+			)),
+		}
+	}
+
+	pub fn reader(&mut self, name: &str) -> Result<Box<dyn Read + '_>, Error> {
+		match self {
+			VfsReader::Dir(root) => Ok(Box::new(File::open(root.join(name))?)),
+			VfsReader::Pk3(archive) => {
+				let stored = Self::stored_name(archive, name)?;
+
+				Ok(Box::new(archive.by_name(&stored).map_err(|e| Error::new(ErrorKind::NotFound, e.to_string()))?))
+			}
+		}
+	}
+
+	// only loose files and pk3 entries stored without compression can seek, a deflated entry is Unsupported
+	pub fn seek_reader(&mut self, name: &str) -> Result<Box<dyn ReadSeek + '_>, Error> {
+		match self {
+			VfsReader::Dir(root) => Ok(Box::new(File::open(root.join(name))?)),
+			VfsReader::Pk3(archive) => {
+				let stored = Self::stored_name(archive, name)?;
+
+				Ok(Box::new(archive.by_name_seek(&stored).map_err(|e| Error::new(ErrorKind::Unsupported, e.to_string()))?))
+			}
+		}
+	}
+
+	// every file in the source, a search path directory lists its loose files the way list_sources does
+	pub fn names(&self) -> Result<Vec<String>, Error> {
+		match self {
+			VfsReader::Dir(root) => Ok(loose_names(root, &walk_search_path(root)?.1)),
+			VfsReader::Pk3(archive) => Ok(archive.file_names().filter(|n| !n.ends_with('/')).map(String::from).collect()),
+		}
+	}
+
+	pub fn read(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+		let mut buf: Vec<u8> = vec![];
+		self.reader(name)?.read_to_end(&mut buf)?;
+
+		Ok(buf)
+	}
+
+	// the year a pk3 entry was last modified, loose files don't carry one
+	pub fn year(&mut self, name: &str) -> Option<u16> {
+		match self {
+			VfsReader::Dir(_) => None,
+			VfsReader::Pk3(archive) => {
+				let stored = Self::stored_name(archive, name).ok()?;
+				archive.by_name(&stored).ok()?.last_modified().map(|d| d.year())
+			}
+		}
+	}
+
+	// crc32 and size from the zip headers, nothing is decompressed, loose files don't carry one
+	pub fn checksum(&mut self, name: &str) -> Option<(u32, u64)> {
+		match self {
+			VfsReader::Dir(_) => None,
+			VfsReader::Pk3(archive) => {
+				let index = archive.index_for_name(&Self::stored_name(archive, name).ok()?)?;
+				let file = archive.by_index_raw(index).ok()?;
+
+				Some((file.crc32(), file.size()))
+			}
+		}
+	}
+
+	// empty files and folders are left out like FS_LoadZipFile does
+	pub fn pk3_checksum(&mut self) -> Option<Pk3Checksum> {
+		let VfsReader::Pk3(archive) = self else { return None };
+		let mut crcs: Vec<u32> = vec![];
+
+		for i in 0..archive.len() {
+			let file = archive.by_index_raw(i).ok()?;
+
+			if file.size() > 0 {
+				crcs.push(file.crc32());
+			}
+		}
+
+		Some(Pk3Checksum::from_crcs(crcs))
+	}
+
+	// zip lookups are case sensitive, the game's aren't
+	fn stored_name(archive: &ZipArchive<File>, name: &str) -> Result<String, Error> {
+		if archive.index_for_name(name).is_some() {
+			return Ok(name.to_string());
+		}
+
+		archive
+			.file_names()
+			.find(|n| n.eq_ignore_ascii_case(name))
+			.map(String::from)
+			.ok_or(Error::new(ErrorKind::NotFound, format!("{} is not in the pk3", name)))
+	}
+}
+
+// the names of a search path's loose files as list_sources walked them, no need to walk it again
+pub fn loose_names(root: &Path, files: &[FileStamp]) -> Vec<String> {
+	files.iter().map(|(path, _, _)| relative_name(root, path)).collect()
+}
+
+// relative to the search path with / separators, like pk3 entries
+fn relative_name(root: &Path, path: &Path) -> String {
+	path.strip_prefix(root).unwrap_or(path).iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn is_pk3(path: &Path) -> bool {
+	path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pk3"))
+}
+
+/*
+    the pk3s directly in a search path and every loose file under it, which is as deep as the game looks for either
+    the folders the game only writes into are skipped, a demos or screenshots folder can hold more files than everything else
+*/
+fn walk_search_path(root: &Path) -> Result<(Vec<FileStamp>, Vec<FileStamp>), Error> {
+	let mut pk3s: Vec<FileStamp> = vec![];
+	let mut loose: Vec<FileStamp> = vec![];
+
+	for (path, metadata) in dir_entries(root)? {
+		let skipped = path.file_name().is_some_and(|n| OUTPUT_DIRS.iter().any(|d| n.eq_ignore_ascii_case(d)));
+
+		if metadata.is_dir() {
+			if !skipped {
+				walk(&path, &mut loose)?;
+			}
+		} else if is_pk3(&path) {
+			pk3s.push((path, metadata.modified().ok(), metadata.len()));
+		} else {
+			loose.push((path, metadata.modified().ok(), metadata.len()));
+		}
+	}
+
+	Ok((pk3s, loose))
+}
+
+fn walk(dir: &Path, walked: &mut Vec<FileStamp>) -> Result<(), Error> {
+	for (path, metadata) in dir_entries(dir)? {
+		if metadata.is_dir() {
+			walk(&path, walked)?;
+		} else {
+			walked.push((path, metadata.modified().ok(), metadata.len()));
+		}
+	}
+
+	Ok(())
+}
+
+// sorted so walks always come out in the same order
+fn dir_entries(dir: &Path) -> Result<Vec<(PathBuf, Metadata)>, Error> {
+	let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+	entries.sort();
+
+	Ok(entries
+		.into_iter()
+		.filter_map(|path| match std::fs::metadata(&path) {
+			Ok(m) => Some((path, m)),
+			Err(e) => {
+				log::error!("Could not read {}: {}", path.to_string_lossy(), e);
+				None
+			}
+		})
+		.collect())
+}
@@ -0,0 +1,70 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+
+use crate::q3_util::{glob_regex, parse_colorstring};
+use crate::server::Quake3Server;
+
+/*
+    a watched name is matched against the plain player name, colour codes are stripped from plain patterns too
+    without regex set, * and ? work as wildcards and everything else must match exactly
+    matching ignores case either way
+*/
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchedPlayer {
+	pub pattern: String,
+	#[serde(default)]
+	pub regex: bool,
+}
+
+impl WatchedPlayer {
+	pub fn matcher(&self) -> Result<Regex, Error> {
+		// ^ starts a colour code in a name but an anchor in a regex, only strip colours from plain patterns
+		let matcher = if self.regex {
+			RegexBuilder::new(&self.pattern).case_insensitive(true).build()
+		} else {
+			glob_regex(&parse_colorstring(&self.pattern).0)
+		};
+
+		matcher.map_err(|e| Error::new(ErrorKind::InvalidInput, format!("bad watchlist pattern {}: {}", self.pattern, e)))
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchMatch {
+	pub pattern: String,
+	pub name: String,
+	pub namecolored: String,
+	pub address: String,
+	pub host: String,
+	pub map: String,
+}
+
+pub fn find_watched_players(watchlist: &[WatchedPlayer], servers: &[Quake3Server]) -> Vec<WatchMatch> {
+	let mut matches: Vec<WatchMatch> = vec![];
+
+	let matchers: Vec<(&WatchedPlayer, Regex)> = watchlist
+		.iter()
+		.filter_map(|w| w.matcher().inspect_err(|e| log::error!("{}", e)).ok().map(|m| (w, m)))
+		.collect();
+
+	for server in servers {
+		let Some(players) = &server.players else { continue };
+
+		for player in players {
+			// first pattern that matches wins so a player isn't listed twice
+			if let Some((watched, _)) = matchers.iter().find(|(_, m)| m.is_match(&player.name)) {
+				matches.push(WatchMatch {
+					pattern: watched.pattern.clone(),
+					name: player.name.clone(),
+					namecolored: player.namecolored.clone(),
+					address: server.address.to_string(),
+					host: server.host.clone(),
+					map: server.map.clone(),
+				});
+			}
+		}
+	}
+
+	matches
+}
@@ -1,9 +1,9 @@
 import { onMounted, ref, computed, readonly } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
-import type { AppData } from '@/models/config'
+import type { AppData, WatchedPlayer } from '@/models/config'
 import { ensureError, defaultAppData } from '@/utils/util'
 import { error } from '@tauri-apps/plugin-log'
-import type { MasterServer } from '@/models/master'
+import type { MasterServer, MasterStatus } from '@/models/master'
 
 const appdata = ref<AppData>(defaultAppData())
 const loaded = ref(false)
@@ -58,11 +58,40 @@ export function useAppData() {
         if (appdata.value.masters[ind]!.active != m.active) {
           appdata.value.masters[ind]!.active = m.active
         }
+        appdata.value.masters[ind]!.gamename = m.gamename
+        appdata.value.masters[ind]!.filters = m.filters
+        appdata.value.masters[ind]!.protocol = m.protocol ?? null
       }
     })
     await writeAppData()
   }
 
+  async function addWatchedPlayer(player: WatchedPlayer) {
+    if (appdata.value.watchlist.some((w) => w.pattern == player.pattern && w.regex == player.regex)) { return }
+    appdata.value.watchlist.push(player)
+    await writeAppData()
+  }
+
+  async function removeWatchedPlayer(player: WatchedPlayer) {
+    appdata.value.watchlist = appdata.value.watchlist.filter((w) => !(w.pattern == player.pattern && w.regex == player.regex))
+    await writeAppData()
+  }
+
+  async function addMasterServer(master: MasterServer) {
+    appdata.value.masters.push(master)
+    await writeAppData()
+  }
+
+  // reachability is saved by the backend after each master query
+  function setMasterStatus(statuses: MasterStatus[]) {
+    statuses.forEach((s) => {
+      const ind = appdata.value.masters.findIndex((n) => n.address === s.master.address && n.game === s.master.game)
+      if (ind != -1) {
+        appdata.value.masters[ind]!.unreachable = !s.reachable
+      }
+    })
+  }
+
   async function writeAppData() {
     const appDataForBackend = {
       ...appdata.value,
@@ -96,6 +125,10 @@ export function useAppData() {
     removeAppData,
     writeAppData,
     updateMasterSettings,
+    addMasterServer,
+    addWatchedPlayer,
+    removeWatchedPlayer,
+    setMasterStatus,
     activeMasterServers,
     setServerPassword,
   }
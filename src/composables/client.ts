@@ -1,6 +1,6 @@
 import { ref, onMounted, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
-import type { Q3Executable, Q3ExecableConfig } from '@/models/client'
+import type { Q3Executable, Q3ExecableConfig, OverrideReport } from '@/models/client'
 import { useConfig } from '@/composables/config'
 import { error } from '@tauri-apps/plugin-log'
 import { ensureError, getClientGameProtocol } from '@/utils/util'
@@ -152,6 +152,16 @@ export function useClient() {
     }
   }
 
+  async function getClientOverrides(client: Q3Executable): Promise<OverrideReport | null> {
+    try {
+      let paths = await getClientPaths(client)
+      return await invoke('get_pk3_overrides', {searchPaths: paths})
+    } catch (err) {
+      error(ensureError(err).message)
+      return null
+    }
+  }
+
   function getClientDefaultGamename(client: Q3Executable): string {
     switch (client.name.toLowerCase()) {
 			case "quake3-urt":
@@ -217,6 +227,7 @@ export function useClient() {
     toggleQ3Client,
     deleteQ3Client,
     pickClient,
-    getClientConfigs
+    getClientConfigs,
+    getClientOverrides
   }
 }
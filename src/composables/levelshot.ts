@@ -3,6 +3,7 @@ import { convertFileSrc } from '@tauri-apps/api/core'
 import { invoke } from '@tauri-apps/api/core'
 import { ensureError } from '@/utils/util'
 import { error } from '@tauri-apps/plugin-log'
+import { type Level } from '@/models/level'
 
 const levelshots = ref<{ [key: string]: string } >({})
 const loaded = ref(false)
@@ -34,6 +35,16 @@ export function useLevelshot() {
     return extracted
   }
 
+  async function extractOverviews(levels: Level[]): Promise<number> {
+    let extracted = 0
+    try {
+      extracted = await invoke('extract_overviews_to_cache', { levels: levels })
+    } catch (err) {
+      error(`Error rendering overviews: ${ensureError(err).message}`)
+    }
+    return extracted
+  }
+
   function levelHasLevelshot(levelName: string) {
     return levelName.toLowerCase() in levelshots.value
   }
@@ -49,6 +60,7 @@ export function useLevelshot() {
     levelshots,
     levelHasLevelshot,
     getCachedLevelshots,
-    extractLevelshots
+    extractLevelshots,
+    extractOverviews
   }
 }
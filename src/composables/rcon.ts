@@ -0,0 +1,43 @@
+import { ref } from 'vue'
+import { invoke } from '@tauri-apps/api/core'
+import { ensureError } from '@/utils/util'
+import { error } from '@tauri-apps/plugin-log'
+
+export function useRcon(address: string) {
+  const output = ref('')
+  const history = ref<string[]>([])
+  const hasPassword = ref(false)
+
+  async function loadRcon() {
+    try {
+      history.value = await invoke('get_rcon_history', { address: address })
+      hasPassword.value = await invoke('has_rcon_password', { address: address })
+    } catch (err) {
+      error(`Error loading rcon for ${address}: ${ensureError(err).message}`)
+    }
+  }
+
+  async function setPassword(password: string) {
+    await invoke('set_rcon_password', { address: address, password: password })
+    hasPassword.value = password != ''
+  }
+
+  async function sendCommand(command: string, timeout: number) {
+    try {
+      output.value += `] ${command}\n`
+      output.value += await invoke('send_rcon_command', { address: address, command: command, timeout: timeout })
+    } catch (err) {
+      output.value += `${ensureError(err).message}\n`
+    }
+    history.value = await invoke('get_rcon_history', { address: address })
+  }
+
+  return {
+    output,
+    history,
+    hasPassword,
+    loadRcon,
+    setPassword,
+    sendCommand,
+  }
+}
@@ -0,0 +1,51 @@
+import { ref } from 'vue'
+import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+import { ensureError } from '@/utils/util'
+import { error } from '@tauri-apps/plugin-log'
+import type { ServerWatch, WatchEvent } from '@/models/server'
+
+const watching = ref<ServerWatch[]>([])
+let unlisten: UnlistenFn | null = null
+
+export function useServerWatch(onEvent: (event: WatchEvent) => void) {
+  async function startWatch(watches: ServerWatch[], interval: number, timeout: number) {
+    try {
+      if (!unlisten) {
+        unlisten = await listen<WatchEvent>('server-watch', (e) => {
+          notify(e.payload)
+          onEvent(e.payload)
+        })
+      }
+      if (Notification.permission == 'default') {
+        await Notification.requestPermission()
+      }
+      await invoke('start_server_watch', { watches: watches, interval: interval, timeout: timeout })
+      watching.value = watches
+    } catch (err) {
+      error(`Error starting server watch: ${ensureError(err).message}`)
+      throw err
+    }
+  }
+
+  async function stopWatch() {
+    await invoke('stop_server_watch')
+    watching.value = []
+    if (unlisten) {
+      unlisten()
+      unlisten = null
+    }
+  }
+
+  function notify(event: WatchEvent) {
+    if (Notification.permission == 'granted') {
+      new Notification('Sarge Launcher', { body: event.message })
+    }
+  }
+
+  return {
+    watching,
+    startWatch,
+    stopWatch,
+  }
+}
@@ -4,8 +4,9 @@ import { sep } from '@tauri-apps/api/path'
 import type { Demo } from '@/models/demo'
 import { useConfig } from '@/composables/config'
 import { useClient } from './client'
-import { getServerProtocol} from '@/utils/util'
-import type { Quake3Server } from '@/models/server'
+import { error } from '@tauri-apps/plugin-log'
+import { ensureError, getServerProtocol } from '@/utils/util'
+import type { Quake3Server, ServerCompatibility } from '@/models/server'
 import type { Level } from '@/models/level'
 import type { Bot } from '@/models/singleplayer'
 
@@ -31,19 +32,30 @@ export function useSpawnQuake() {
     }
   }
 
-  async function spawnQuakeServer(selectedServer: Quake3Server | null){
-    if (!selectedServer) { return }
+  /*
+    returns what the compatibility check expects to go wrong, joining isn't blocked since missing paks can still be downloaded
+    the check builds a vfs for the server's mod and checksums pk3s, so it only runs once the client is already starting
+  */
+  async function spawnQuakeServer(selectedServer: Quake3Server | null): Promise<string[]> {
+    if (!selectedServer || !activeClient.value) { return [] }
+
+    if (selectedServer.othersettings['g_needpass'] == '1') {
+      throw new Error('needs password')
+    }
+
+    // the fs_game the backend checks against, the server's fs_game cvar or its gamename
+    let fsGame = selectedServer.othersettings['fs_game'] || selectedServer.game
+
+    let args = ['+set', 'fs_game', fsGame, '+set', 'protocol', getServerProtocol(selectedServer), '+connect', selectedServer.address];
+    await spawnQuake(args)
 
     try {
-      let args = ['+set', 'fs_game', selectedServer.game, '+set', 'protocol', getServerProtocol(selectedServer), '+connect', selectedServer.address];
-      if ('g_needpass' in selectedServer.othersettings && selectedServer.othersettings['g_needpass'] == '1') {
-        throw new Error('needs password')
-      } else {
-        spawnQuake(args)
-      }
-    } catch(err) {
-      throw err
-    }       
+      let compat: ServerCompatibility = await invoke('check_server_compatibility', { server: selectedServer, client: activeClient.value })
+      return compat.problems
+    } catch (err) {
+      error(ensureError(err).message)
+      return []
+    }
   }
 
   async function spawnQuakeDemo(selectedDemo: Demo | null) {
@@ -10,4 +10,34 @@ export interface Q3Executable {
 export interface Q3ExecableConfig {
   name: string
   path: string
-}
\ No newline at end of file
+}
+
+export interface FileOverride {
+  name: string
+  loaded_from: string
+  overridden: string[]
+  // every copy has the same crc and size
+  identical: boolean
+}
+
+export interface Pk3Overrides {
+  path: string
+  files: number
+  overridden: number
+  overrides: number
+  fully_shadowed: boolean
+}
+
+export interface OverrideReport {
+  files: FileOverride[]
+  pk3s: Pk3Overrides[]
+}
+
+// sv_referencedPakNames lists gamename/name, sv_pakNames only the name
+export interface PakChecksum {
+  path: string
+  gamename: string
+  name: string
+  checksum: number
+  pure_checksum: number | null
+}
@@ -17,6 +17,7 @@ export interface Config {
 }
 
 export interface AppData {
+  version: number
   path: string
   pinned: Set<string>
   custom: Set<string>
@@ -24,4 +25,27 @@ export interface AppData {
   trash_ip: Set<string>
   server_password: string
   masters: MasterServer[]
+  rcon: { [address: string]: RconServer }
+  watchlist: WatchedPlayer[]
+}
+
+// without regex, * and ? are wildcards, names match without colours and ignoring case
+export interface WatchedPlayer {
+  pattern: string
+  regex: boolean
+}
+
+export interface WatchMatch {
+  pattern: string
+  name: string
+  namecolored: string
+  address: string
+  host: string
+  map: string
+}
+
+// the password is stored obfuscated, only the backend reads it
+export interface RconServer {
+  password: string
+  history: string[]
 }
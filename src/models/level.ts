@@ -1,4 +1,5 @@
 export interface Level {
+  // empty for loose maps/*.bsp files
   pk3_name: string
   level_name: string
   long_name: string
@@ -9,4 +10,40 @@ export interface Level {
   parent_path: string
   is_defrag: boolean
   year_created: number
+  entities: LevelEntities | null
+  gametype_inferred: boolean
+  // the copy the game loads instead, null when this one is loaded
+  shadowed_by: string | null
+  // the pk3's checksum as servers list it in sv_paks, null for loose maps
+  pk3_checksum: number | null
+}
+
+// from the bsp entity lump, counts are keyed by classname
+export interface LevelEntities {
+  spawns: { ffa: number; red: number; blue: number }
+  weapons: { [classname: string]: number }
+  items: { [classname: string]: number }
+  armor: { [classname: string]: number }
+  powerups: { [classname: string]: number }
+  message: string
+  music: string
+  ctf_flags: boolean
+  neutral_flag: boolean
+  race_timers: boolean
+}
+
+export interface MapDependency {
+  name: string
+  kind: 'shader' | 'texture' | 'model'
+  // pk3 or loose file it loads from, null when missing
+  provided_by: string | null
+  // shader that loads this texture, empty for what the bsp references itself
+  required_by: string
+}
+
+export interface MapDependencies {
+  level_name: string
+  bsp: string
+  dependencies: MapDependency[]
+  missing: number
 }
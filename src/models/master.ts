@@ -4,4 +4,17 @@ export interface MasterServer {
   game: string
   active: boolean
   unreachable: boolean
+  gamename: string
+  filters: string
+  protocol: number | null
+}
+
+export interface MasterStatus {
+  master: MasterServer
+  reachable: boolean
+  latency: number
+  servers: number
+  duplicates: number
+  complete: boolean
+  error: string | null
 }
@@ -5,12 +5,17 @@ export interface ServerPlayer {
   namecolored: string
   frags: number
   ping: number
+  team: string
+  spectator: boolean
+}
+
+export interface TeamScores {
+  red: number
+  blue: number
 }
 
 export interface Quake3Server {
   master: MasterServer | null
-  ip: string
-  port: string
   address: string
   protocol: number | null
   ping: number
@@ -24,7 +29,95 @@ export interface Quake3Server {
   map: string
   othersettings: { [key: string]: string }
   players: ServerPlayer[] | null
+  teamscores: TeamScores | null
   list: string
   custom: boolean
   version: string
 }
+
+// a mismatch is a pk3 with the referenced name but another checksum, the client won't download over it
+export interface ServerPak {
+  name: string
+  checksum: number
+  state: 'ok' | 'missing' | 'mismatch'
+  local_path: string | null
+}
+
+export interface ServerCompatibility {
+  fs_game: string
+  has_mod: boolean
+  map: string
+  has_map: boolean
+  pure: boolean
+  // empty when the server doesn't send its paks in the status response
+  paks: ServerPak[]
+  // g_needpass, the only thing that stops a join
+  needs_password: boolean
+  private_slots: number
+  // only sv_privateClients slots are left, those take the private password
+  public_slots_full: boolean
+  version: string
+  problems: string[]
+}
+
+export interface ServerSample {
+  address: string
+  time: number
+  players: number
+  bots: number
+  map: string
+  ping: number
+  reachable: boolean
+}
+
+// hourly_players and peak_hours are in local time when queried with -new Date().getTimezoneOffset()
+export interface ServerStats {
+  address: string
+  samples: number
+  first_seen: number | null
+  last_seen_online: number | null
+  uptime: number
+  average_players: number
+  typical_players: number
+  peak_players: number
+  hourly_players: number[]
+  peak_hours: number[]
+  maps: [string, number][]
+}
+
+// filters applied in the backend to the last refreshed servers, unset fields don't filter
+export interface ServerQuery {
+  search?: string
+  game?: string
+  map?: string
+  gametype?: string
+  min_players?: number
+  max_players?: number
+  exclude_bots?: boolean
+  max_ping?: number
+  version?: string
+  has_password?: boolean
+  cvars?: { [key: string]: string }
+  include_unreachable?: boolean
+  sort?: 'game' | 'host' | 'map' | 'playersconnected' | 'ping' | 'address'
+  descending?: boolean
+}
+
+export type WatchCondition =
+  | { type: 'online' }
+  | { type: 'slot_free' }
+  | { type: 'min_players'; count: number }
+  | { type: 'map'; name: string }
+  | { type: 'player_joined'; player: { pattern: string; regex: boolean } }
+
+export interface ServerWatch {
+  address: string
+  conditions: WatchCondition[]
+}
+
+export interface WatchEvent {
+  address: string
+  host: string
+  condition: WatchCondition
+  message: string
+}
@@ -33,6 +33,7 @@ export function defaultConfig(): Config {
 
 export function defaultAppData(): AppData {
   return {
+    version: 0,
     path: '',
     pinned: new Set(),
     custom: new Set(),
@@ -40,14 +41,14 @@ export function defaultAppData(): AppData {
     trash_ip: new Set(),
     masters: [],
     server_password: '',
+    rcon: {},
+    watchlist: [],
   }
 }
 
-export function newCustomServer(ip_and_port: string[], address: string): Quake3Server {
+export function newCustomServer(address: string): Quake3Server {
   return {
     master: null,
-    ip: ip_and_port[0]!,
-    port: ip_and_port[1]!,
     address: address,
     protocol: null,
     ping: 0,
@@ -61,13 +62,26 @@ export function newCustomServer(ip_and_port: string[], address: string): Quake3S
     map: '',
     othersettings: {},
     players: null,
+    teamscores: null,
     list: 'pinned',
     custom: true,
     version: '',
   }
 }
 
+export function serverIp(address: string): string {
+  if (address.startsWith('[')) {
+    return address.slice(1, address.indexOf(']'))
+  }
+  return address.split(':')[0]!
+}
+
 export function validServerAddress(input: string): boolean {
+  // [ipv6]:port
+  if (/^\[[0-9a-fA-F:.]+\]:\d{1,5}$/.test(input)) {
+    return true
+  }
+
   if (!input.includes(':') || !input.includes('.')) {
     return false
   }